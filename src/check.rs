@@ -0,0 +1,354 @@
+use std::fmt::{
+	Display,
+	Formatter,
+};
+
+use crate::{
+	config::{
+		Config,
+		FileReference,
+		TableOfContentEntry,
+	},
+	limits::{
+		ACTION_ROWS,
+		ATTACHMENT_SIZE,
+		AUTOCOMPLETE_CHOICE_LENGTH,
+		BUTTONS_PER_ROW,
+		BUTTON_LABEL_LENGTH,
+		CHANNEL_TOPIC_LENGTH,
+		CUSTOM_ID_LENGTH,
+		EMBEDS_PER_MESSAGE,
+		EMBED_FIELDS,
+		EMBED_FIELD_LENGTH,
+		EMBED_FOOTER_LENGTH,
+		EMBED_LENGTH,
+		EMBED_TITLE_LENGTH,
+		MESSAGE_LENGTH,
+		SELECT_MENU_OPTIONS,
+		SELECT_OPTION_LENGTH,
+		SELECT_PLACEHOLDER_LENGTH,
+	},
+	toc,
+};
+
+/// A single problem found in the configuration.
+pub struct Problem {
+	location: String,
+	message: String,
+}
+
+impl Display for Problem {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.location, self.message)
+	}
+}
+
+/// Collects problems while walking the configuration.
+#[derive(Default)]
+struct Report {
+	problems: Vec<Problem>,
+}
+
+impl Report {
+	fn problem(&mut self, location: impl Into<String>, message: impl Into<String>) {
+		self.problems.push(Problem {
+			location: location.into(),
+			message: message.into(),
+		});
+	}
+
+	fn max_length(&mut self, location: impl Into<String>, what: &str, value: &str, limit: usize) {
+		let length = value.chars().count();
+		if length > limit {
+			self.problem(
+				location,
+				format!("{} is {} characters long, limit is {}", what, length, limit),
+			);
+		}
+	}
+}
+
+/// Validates the configuration file at the given path without connecting to Discord and prints a report. Returns the
+/// process exit code, so broken configurations can be rejected before they are deployed.
+pub fn run(path: &str) -> i32 {
+	let config = match Config::load(path) {
+		Ok(config) => config,
+		Err(err) => {
+			println!("{}", err);
+			println!("Configuration is invalid.");
+			return 1;
+		},
+	};
+
+	let problems = check(&config);
+	if problems.is_empty() {
+		println!("Configuration is valid.");
+		return 0;
+	}
+
+	for problem in &problems {
+		println!("{}", problem);
+	}
+	println!("Configuration has {} problem(s).", problems.len());
+	1
+}
+
+/// Checks a parsed configuration for violations of Discord's limits.
+pub fn check(config: &Config) -> Vec<Problem> {
+	let mut report = Report::default();
+
+	for (idx, policy) in config.self_managment.policies.iter().enumerate() {
+		if config.self_managment.policies[..idx]
+			.iter()
+			.any(|other| other.role == policy.role)
+		{
+			let location = format!("self_managment.policies[{}]", idx);
+			report.problem(&location, format!("role {} is used multiple times", policy.role));
+		}
+	}
+	for (name, template) in &config.self_managment.templates {
+		let location = format!("self_managment.templates.{}", name);
+		report.max_length(&location, "name", name, AUTOCOMPLETE_CHOICE_LENGTH);
+		if let Some(topic) = &template.topic {
+			report.max_length(&location, "topic", topic, CHANNEL_TOPIC_LENGTH);
+		}
+		if let Some(welcome) = &template.welcome {
+			forbid_front_matter(&mut report, &location, welcome);
+			report.max_length(
+				&location,
+				&format!("content of {}", welcome.filename),
+				&welcome.content,
+				MESSAGE_LENGTH,
+			);
+		}
+	}
+
+	// welcome message buttons, see `toc::get_toc_buttons`
+	let mut explicit = [0; ACTION_ROWS];
+	explicit[0] = 1;
+	for (idx, entry) in config.toc.iter().enumerate() {
+		let Some(row) = entry.row else {
+			continue;
+		};
+		let location = format!("toc[{}]", idx);
+		if !(1..=ACTION_ROWS).contains(&row) {
+			report.problem(&location, format!("row {} is not between 1 and {}", row, ACTION_ROWS));
+			continue;
+		}
+		explicit[row - 1] += 1;
+		if explicit[row - 1] == BUTTONS_PER_ROW + 1 {
+			report.problem(
+				&location,
+				format!(
+					"row {} has more buttons (including assignments) than the limit of {}",
+					row, BUTTONS_PER_ROW
+				),
+			);
+		}
+	}
+	if toc::assign_rows(&config.toc).is_none() {
+		// entries are offered in a select menu instead
+		if config.toc.len() > SELECT_MENU_OPTIONS {
+			report.problem(
+				"toc",
+				format!(
+					"{} entries exceed the limit of {} select menu options",
+					config.toc.len(),
+					SELECT_MENU_OPTIONS
+				),
+			);
+		}
+		for (idx, entry) in config.toc.iter().enumerate() {
+			let location = format!("toc[{}]", idx);
+			report.max_length(&location, "label", &entry.label, SELECT_OPTION_LENGTH);
+			report.max_length(&location, "file name", &entry.file.filename, SELECT_OPTION_LENGTH);
+		}
+	}
+	report.max_length(
+		"self_assignments.label",
+		"label",
+		&config.self_assignments.label,
+		BUTTON_LABEL_LENGTH,
+	);
+	check_front_matter(&mut report, "welcome", &config.welcome);
+	check_toc_entries(&mut report, "toc", "toc:", &config.toc);
+
+	// role assignment menus, see `toc::print_assignments`
+	forbid_front_matter(&mut report, "self_assignments.prolog", &config.self_assignments.prolog);
+	report.max_length(
+		"self_assignments.prolog",
+		&format!("content of {}", config.self_assignments.prolog.filename),
+		&config.self_assignments.prolog.content,
+		MESSAGE_LENGTH,
+	);
+	if config.assignments.len() > ACTION_ROWS {
+		report.problem(
+			"assignments",
+			format!(
+				"{} assignments exceed the limit of {} select menus per message",
+				config.assignments.len(),
+				ACTION_ROWS
+			),
+		);
+	}
+	for (id, assignment) in &config.assignments {
+		let location = format!("assignments.{}", id);
+		report.max_length(&location, "custom id", &format!("assign:{}", id), CUSTOM_ID_LENGTH);
+		report.max_length(&location, "title", &assignment.title, SELECT_PLACEHOLDER_LENGTH);
+		if assignment.roles.is_empty() {
+			report.problem(&location, "assignment has no roles");
+		}
+		if assignment.roles.len() > SELECT_MENU_OPTIONS {
+			report.problem(
+				&location,
+				format!(
+					"{} roles exceed the limit of {} options per select menu",
+					assignment.roles.len(),
+					SELECT_MENU_OPTIONS
+				),
+			);
+		}
+		for (idx, role) in assignment.roles.iter().enumerate() {
+			let location = format!("{}.roles[{}]", location, idx);
+			report.max_length(&location, "label", &role.label, SELECT_OPTION_LENGTH);
+			if let Some(subscript) = &role.subscript {
+				report.max_length(&location, "subscript", subscript, SELECT_OPTION_LENGTH);
+			}
+			if assignment.roles[..idx].iter().any(|other| other.role == role.role) {
+				report.problem(&location, format!("role {} is used multiple times", role.role));
+			}
+		}
+	}
+
+	report.problems
+}
+
+/// Checks the embed limits of a file's front-matter, see `toc::split_file`.
+fn check_front_matter(report: &mut Report, location: &str, file: &FileReference) {
+	let Some(front_matter) = &file.front_matter else {
+		return;
+	};
+
+	let location = format!("{} ({})", location, file.filename);
+	if let Some(title) = &front_matter.title {
+		report.max_length(&location, "title", title, EMBED_TITLE_LENGTH);
+	}
+	if let Some(footer) = &front_matter.footer {
+		report.max_length(&location, "footer", footer, EMBED_FOOTER_LENGTH);
+	}
+	if let Some(thumbnail) = &front_matter.thumbnail {
+		if !thumbnail.starts_with("https://") && !thumbnail.starts_with("http://") {
+			report.problem(&location, format!("thumbnail {} is not a http(s) url", thumbnail));
+		}
+	}
+	if front_matter.fields.len() > EMBED_FIELDS {
+		report.problem(
+			&location,
+			format!(
+				"{} fields exceed the limit of {} per embed",
+				front_matter.fields.len(),
+				EMBED_FIELDS
+			),
+		);
+	}
+	for (idx, field) in front_matter.fields.iter().enumerate() {
+		let location = format!("{}.fields[{}]", location, idx);
+		report.max_length(&location, "name", &field.name, EMBED_TITLE_LENGTH);
+		report.max_length(&location, "value", &field.value, EMBED_FIELD_LENGTH);
+	}
+
+	// the description gets whatever is left, so there has to be some room for it
+	if front_matter.length() >= EMBED_LENGTH {
+		report.problem(
+			&location,
+			format!(
+				"front-matter is {} characters long, limit of all embed texts is {}",
+				front_matter.length(),
+				EMBED_LENGTH
+			),
+		);
+	}
+}
+
+/// Reports a front-matter in files which are always sent as plain text.
+fn forbid_front_matter(report: &mut Report, location: &str, file: &FileReference) {
+	if file.front_matter.is_some() {
+		report.problem(location, format!("file {} must not have a front-matter", file.filename));
+	}
+}
+
+/// Checks toc entries and all of their sub-entries, see `toc::handle_toc_click`.
+fn check_toc_entries(report: &mut Report, location: &str, custom_id: &str, entries: &[TableOfContentEntry]) {
+	for (idx, entry) in entries.iter().enumerate() {
+		let location = format!("{}[{}]", location, idx);
+		let custom_id = format!("{}{}", custom_id, entry.file.filename);
+		report.max_length(&location, "label", &entry.label, BUTTON_LABEL_LENGTH);
+		check_front_matter(report, &location, &entry.file);
+
+		// uploaded attachments are linked as embeds afterwards, next to the embed of the page itself
+		let max_attachments = EMBEDS_PER_MESSAGE - 1;
+		if entry.attachments.len() > max_attachments {
+			report.problem(
+				&location,
+				format!(
+					"{} attachments exceed the limit of {} per page",
+					entry.attachments.len(),
+					max_attachments
+				),
+			);
+		}
+		let size = entry
+			.attachments
+			.iter()
+			.map(|attachment| attachment.data.len())
+			.sum::<usize>();
+		if size > ATTACHMENT_SIZE {
+			report.problem(
+				&location,
+				format!("attachments are {} bytes large, limit is {}", size, ATTACHMENT_SIZE),
+			);
+		}
+		report.max_length(&location, "custom id", &custom_id, CUSTOM_ID_LENGTH);
+
+		// long entries are split into pages, whose buttons carry the page number as well
+		let pages = toc::split_file(&entry.file).len();
+		if pages > 1 {
+			let page_id = format!("{}@{}", custom_id, pages - 1);
+			report.max_length(&location, "custom id of last page", &page_id, CUSTOM_ID_LENGTH);
+		}
+
+		// entries are looked up by filename, so siblings must not share one
+		if entries[..idx].iter().any(|other| other.file.filename == entry.file.filename) {
+			report.problem(&location, format!("file {} is used by multiple entries", entry.file.filename));
+		}
+		if entry.file.filename.contains(['>', '@']) {
+			report.problem(&location, format!("file {} must not contain `>` or `@`", entry.file.filename));
+		}
+
+		// one row is reserved for the back and page buttons
+		let max_entries = BUTTONS_PER_ROW * (ACTION_ROWS - 1);
+		if entry.entries.len() > max_entries {
+			report.problem(
+				&location,
+				format!(
+					"{} sub-entries exceed the limit of {} buttons per page",
+					entry.entries.len(),
+					max_entries
+				),
+			);
+		}
+		// rows only exist on the welcome message
+		for (sub_idx, sub_entry) in entry.entries.iter().enumerate() {
+			if sub_entry.row.is_some() {
+				let location = format!("{}.entries[{}]", location, sub_idx);
+				report.problem(&location, "row is only supported for top level entries");
+			}
+		}
+		check_toc_entries(
+			report,
+			&format!("{}.entries", location),
+			&format!("{}>", custom_id),
+			&entry.entries,
+		);
+	}
+}
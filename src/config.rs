@@ -3,11 +3,19 @@ use std::{
 		Display,
 		Formatter,
 	},
-	fs::read_to_string,
+	fs::{
+		read,
+		read_to_string,
+	},
+	path::Path,
 };
 
 use linked_hash_map::LinkedHashMap;
-use poise::serenity_prelude::ReactionType;
+use poise::serenity_prelude::{
+	Permissions,
+	ReactionType,
+};
+use regex::Regex;
 use serde::{
 	de::{
 		Error,
@@ -19,12 +27,16 @@ use serde::{
 use serde_with::{
 	serde_as,
 	DisplayFromStr,
+	OneOrMany,
 };
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
 	pub bot_token: String,
+	/// Path of the SQLite database, defaults to `kitmatheinfo-bot.sqlite` in the working directory.
+	#[serde(default = "default_database")]
+	pub database: String,
 	pub welcome: FileReference,
 	pub self_managment: SelfManagement,
 	pub toc: Vec<TableOfContentEntry>,
@@ -34,23 +46,184 @@ pub struct Config {
 	pub o_phase: Option<OPhase>,
 }
 
+impl Config {
+	/// Reads and parses the configuration file at the given path, including all referenced files.
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, crate::Error> {
+		let path = path.as_ref();
+		let content = read_to_string(path).map_err(|err| format!("config file {} could not be read: {}", path.display(), err))?;
+		let config =
+			toml::from_str::<Config>(&content).map_err(|err| format!("config file {} is invalid: {}", path.display(), err))?;
+
+		// everything in the self-managed area assumes there is somewhere to put channels
+		if config.self_managment.category.is_empty() {
+			return Err(
+				format!(
+					"config file {} is invalid: self_managment.category requires at least one category",
+					path.display()
+				)
+				.into(),
+			);
+		}
+
+		Ok(config)
+	}
+}
+
 #[derive(Debug)]
 pub struct FileReference {
 	pub filename: String,
+	/// Content without the front-matter.
 	pub content: String,
+	/// Optional TOML block between `+++` lines at the start of the file, which turns the content into an embed.
+	pub front_matter: Option<FrontMatter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrontMatter {
+	pub title: Option<String>,
+	/// Color of the embed, like `0x5865F2`.
+	pub color: Option<u32>,
+	/// URL of an image shown in the top right corner.
+	pub thumbnail: Option<String>,
+	pub footer: Option<String>,
+	#[serde(default)]
+	pub fields: Vec<EmbedField>,
+}
+
+impl FrontMatter {
+	/// Number of characters counting towards Discord's total embed limit, excluding the description.
+	pub fn length(&self) -> usize {
+		let count = |text: &Option<String>| text.as_deref().map_or(0, |text| text.chars().count());
+		count(&self.title)
+			+ count(&self.footer)
+			+ self
+				.fields
+				.iter()
+				.map(|field| field.name.chars().count() + field.value.chars().count())
+				.sum::<usize>()
+	}
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmbedField {
+	pub name: String,
+	pub value: String,
+	#[serde(default)]
+	pub inline: bool,
 }
 
+/// Local file which is sent as attachment, its data is read when the configuration is loaded.
+#[derive(Debug)]
+pub struct AttachmentFile {
+	pub path: String,
+	/// Name of the file without its directories, as shown in Discord.
+	pub filename: String,
+	pub data: Vec<u8>,
+}
+
+#[serde_as]
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SelfManagement {
-	pub category: u64,
+	#[serde_as(as = "OneOrMany<_>")]
+	pub category: Vec<u64>,
 	pub ownership: bool,
 	pub limit: u64,
 	pub join_age_limit: i64,
 	pub abandon_after: u64,
 	pub claiming: bool,
+	#[serde(default)]
+	pub objection_period: u64,
+	#[serde(default)]
+	pub sorting: SortStrategy,
 	pub logging: Option<u64>,
 	pub logging_detailed: Option<u64>,
+	pub archive: Option<Archive>,
+	pub sweeper: Option<Sweeper>,
+	pub names: Option<ChannelNames>,
+	pub voice_lobby: Option<VoiceLobby>,
+	#[serde(default)]
+	pub templates: LinkedHashMap<String, ChannelTemplate>,
+	#[serde(default)]
+	pub policies: Vec<RolePolicy>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortStrategy {
+	#[default]
+	Alphabetical,
+	Activity,
+	Created,
+	PinnedFirst,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Archive {
+	pub category: u64,
+	pub retention: u64,
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelNames {
+	pub max_length: usize,
+	#[serde(default)]
+	pub reserved: Vec<String>,
+	#[serde_as(as = "Vec<DisplayFromStr>")]
+	#[serde(default)]
+	pub blocklist: Vec<Regex>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RolePolicy {
+	pub role: u64,
+	pub limit: Option<u64>,
+	pub join_age_limit: Option<i64>,
+	#[serde(default)]
+	pub deny: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelTemplate {
+	pub topic: Option<String>,
+	#[serde(default)]
+	pub slowmode: u16,
+	#[serde(default)]
+	pub nsfw: bool,
+	pub welcome: Option<FileReference>,
+	#[serde(default)]
+	pub permissions: Vec<TemplatePermission>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplatePermission {
+	pub role: u64,
+	#[serde(default, deserialize_with = "deserialize_permissions")]
+	pub allow: Permissions,
+	#[serde(default, deserialize_with = "deserialize_permissions")]
+	pub deny: Permissions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VoiceLobby {
+	pub channel: u64,
+	pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sweeper {
+	pub inactive_after: u64,
+	pub grace_period: u64,
 }
 
 #[serde_as]
@@ -71,6 +244,13 @@ pub struct TableOfContentEntry {
 	#[serde_as(as = "DisplayFromStr")]
 	pub icon: ReactionType,
 	pub file: FileReference,
+	/// Row (from 1 to 5) of the welcome message in which the button is placed, only used for top level entries.
+	pub row: Option<usize>,
+	/// Files uploaded alongside the last page of the entry, like a campus map.
+	#[serde(default)]
+	pub attachments: Vec<AttachmentFile>,
+	#[serde(default)]
+	pub entries: Vec<TableOfContentEntry>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -106,6 +286,60 @@ pub struct OPhase {
 	pub channel_name: String,
 }
 
+/// Database path used if the config doesn't specify one, relative to the working directory.
+fn default_database() -> String {
+	"kitmatheinfo-bot.sqlite".to_string()
+}
+
+/// Deserializes a list of permission names like `SEND_MESSAGES`.
+fn deserialize_permissions<'de, D>(deserializer: D) -> Result<Permissions, D::Error>
+where D: Deserializer<'de> {
+	Vec::<String>::deserialize(deserializer)?
+		.iter()
+		.try_fold(Permissions::empty(), |permissions, name| {
+			let permission = Permissions::from_name(name).ok_or_else(|| Error::custom(format!("unknown permission {}", name)))?;
+			Ok(permissions | permission)
+		})
+}
+
+impl<'de> Deserialize<'de> for AttachmentFile {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where D: Deserializer<'de> {
+		let path = String::deserialize(deserializer)?;
+		let data = read(&path).map_err(|err| Error::custom(format!("file {} could not be read: {}", &path, err)))?;
+		let filename = Path::new(&path)
+			.file_name()
+			.and_then(|name| name.to_str())
+			.ok_or_else(|| Error::custom(format!("file {} has no name", &path)))?
+			.to_string();
+
+		Ok(AttachmentFile {
+			path,
+			filename,
+			data,
+		})
+	}
+}
+
+/// Separates the front-matter of a file from its content, see `FileReference::front_matter`.
+fn split_front_matter(content: &str) -> Result<(Option<FrontMatter>, String), String> {
+	let mut lines = content.split_inclusive('\n');
+	if lines.next().map(str::trim_end) != Some("+++") {
+		return Ok((None, content.to_string()));
+	}
+
+	let mut front_matter = String::new();
+	for line in lines.by_ref() {
+		if line.trim_end() == "+++" {
+			let front_matter = toml::from_str(&front_matter).map_err(|err| format!("front-matter is invalid: {}", err))?;
+			return Ok((Some(front_matter), lines.collect()));
+		}
+		front_matter.push_str(line);
+	}
+
+	Err("front-matter is not terminated by `+++`".to_string())
+}
+
 impl Display for FileReference {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		write!(f, "{}", &self.content)
@@ -138,10 +372,13 @@ impl<'de> Deserialize<'de> for FileReference {
 			where E: Error {
 				let content =
 					read_to_string(&filename).map_err(|err| Error::custom(format!("file {} could not be read: {}", &filename, err)))?;
+				let (front_matter, content) =
+					split_front_matter(&content).map_err(|err| Error::custom(format!("file {} is invalid: {}", &filename, err)))?;
 
 				Ok(FileReference {
 					filename,
 					content,
+					front_matter,
 				})
 			}
 		}
@@ -0,0 +1,201 @@
+use std::{
+	path::Path,
+	sync::{
+		Mutex,
+		MutexGuard,
+	},
+	time::SystemTime,
+};
+
+#[allow(unused_imports)]
+use log::{
+	debug,
+	error,
+	info,
+	trace,
+	warn,
+};
+use rusqlite::Connection;
+
+use crate::Error;
+
+/// Schema migrations, applied in order. The number of applied migrations is tracked in `PRAGMA user_version`, so
+/// existing entries must never be modified, only new ones appended.
+const MIGRATIONS: &[&str] = &[
+	// 1: reports and o-phase password attempts
+	r#"
+	CREATE TABLE reports (
+		id INTEGER PRIMARY KEY,
+		message_id INTEGER NOT NULL,
+		channel_id INTEGER NOT NULL,
+		author_id INTEGER NOT NULL,
+		reporter_id INTEGER NOT NULL,
+		reason TEXT NOT NULL,
+		content TEXT NOT NULL,
+		created_at INTEGER NOT NULL
+	);
+	CREATE INDEX reports_author ON reports (author_id);
+
+	CREATE TABLE ophase_attempts (
+		id INTEGER PRIMARY KEY,
+		user_id INTEGER NOT NULL,
+		success INTEGER NOT NULL,
+		created_at INTEGER NOT NULL
+	);
+	CREATE INDEX ophase_attempts_user ON ophase_attempts (user_id);
+	"#,
+	// 2: ownership of self-managed channels
+	r#"
+	CREATE TABLE channels (
+		channel_id INTEGER PRIMARY KEY,
+		owner_id INTEGER NOT NULL,
+		creator_id INTEGER NOT NULL,
+		created_at INTEGER NOT NULL
+	);
+	CREATE INDEX channels_owner ON channels (owner_id);
+	"#,
+	// 3: co-owners of self-managed channels
+	r#"
+	CREATE TABLE channel_co_owners (
+		channel_id INTEGER NOT NULL REFERENCES channels (channel_id) ON DELETE CASCADE,
+		user_id INTEGER NOT NULL,
+		PRIMARY KEY (channel_id, user_id)
+	);
+	"#,
+	// 4: archived self-managed channels
+	r#"
+	CREATE TABLE archived_channels (
+		channel_id INTEGER PRIMARY KEY,
+		category_id INTEGER NOT NULL,
+		permission_overwrites TEXT NOT NULL,
+		archived_by INTEGER NOT NULL,
+		archived_at INTEGER NOT NULL
+	);
+	"#,
+	// 5: warnings about inactive self-managed channels
+	r#"
+	CREATE TABLE inactivity_warnings (
+		channel_id INTEGER PRIMARY KEY,
+		message_id INTEGER NOT NULL,
+		warned_at INTEGER NOT NULL
+	);
+	"#,
+	// 6: claims of abandoned channels, waiting for objection
+	r#"
+	CREATE TABLE pending_claims (
+		channel_id INTEGER PRIMARY KEY,
+		claimant_id INTEGER NOT NULL,
+		message_id INTEGER NOT NULL,
+		deadline INTEGER NOT NULL
+	);
+	"#,
+	// 7: self-managed channels pinned to the top by moderators
+	r#"
+	CREATE TABLE pinned_channels (
+		channel_id INTEGER PRIMARY KEY,
+		pinned_by INTEGER NOT NULL,
+		pinned_at INTEGER NOT NULL
+	);
+	"#,
+	// 8: users muted by owners of self-managed channels
+	r#"
+	CREATE TABLE channel_mutes (
+		channel_id INTEGER NOT NULL,
+		user_id INTEGER NOT NULL,
+		muted_by INTEGER NOT NULL,
+		expires_at INTEGER NOT NULL,
+		PRIMARY KEY (channel_id, user_id)
+	);
+	CREATE INDEX channel_mutes_expires ON channel_mutes (expires_at);
+	"#,
+	// 9: voice rooms spawned by the join-to-create lobby, deleted once empty
+	r#"
+	CREATE TABLE temporary_rooms (
+		channel_id INTEGER PRIMARY KEY,
+		created_at INTEGER NOT NULL
+	);
+	"#,
+	// 10: audit trail of self-management actions
+	r#"
+	CREATE TABLE audit_events (
+		id INTEGER PRIMARY KEY,
+		channel_id INTEGER NOT NULL,
+		actor_id INTEGER,
+		action TEXT NOT NULL,
+		before TEXT,
+		after TEXT,
+		details TEXT NOT NULL,
+		created_at INTEGER NOT NULL
+	);
+	CREATE INDEX audit_events_channel ON audit_events (channel_id);
+	"#,
+	// 11: messages making up a welcome message, which is split if too long
+	r#"
+	CREATE TABLE welcome_messages (
+		message_id INTEGER PRIMARY KEY,
+		group_id INTEGER NOT NULL,
+		part INTEGER NOT NULL,
+		channel_id INTEGER NOT NULL
+	);
+	CREATE INDEX welcome_messages_group ON welcome_messages (group_id);
+	"#,
+	// 12: last message before notices of the bot, which don't count as activity
+	r#"
+	ALTER TABLE inactivity_warnings ADD COLUMN previous_message_id INTEGER;
+	ALTER TABLE pending_claims ADD COLUMN previous_message_id INTEGER;
+	"#,
+];
+
+/// Embedded database for everything the bot needs to remember across restarts.
+pub struct Database {
+	connection: Mutex<Connection>,
+}
+
+impl Database {
+	/// Opens (or creates) the database at the given path and applies all pending migrations.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+		let path = path.as_ref();
+		let mut connection =
+			Connection::open(path).map_err(|err| format!("database {} could not be opened: {}", path.display(), err))?;
+		connection.pragma_update(None, "foreign_keys", true)?;
+		connection.pragma_update(None, "journal_mode", "WAL")?;
+
+		migrate(&mut connection)?;
+
+		Ok(Database {
+			connection: Mutex::new(connection),
+		})
+	}
+
+	/// Grants exclusive access to the underlying connection. Queries are fast enough to be executed directly from async
+	/// code, but the guard must not be held across await points.
+	pub fn lock(&self) -> MutexGuard<'_, Connection> {
+		self.connection.lock().expect("database lock poisoned")
+	}
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), Error> {
+	let version: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+	if version > MIGRATIONS.len() {
+		return Err(Error::from(format!(
+			"database schema version {} is newer than supported version {}",
+			version,
+			MIGRATIONS.len()
+		)));
+	}
+
+	for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+		info!("Applying database migration {}", idx + 1);
+		let tx = connection.transaction()?;
+		tx.execute_batch(migration)?;
+		tx.pragma_update(None, "user_version", idx + 1)?;
+		tx.commit()?;
+	}
+
+	Ok(())
+}
+
+/// Current time as unix timestamp, which is how all points in time are stored in the database.
+pub fn now() -> i64 {
+	SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64
+}
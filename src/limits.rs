@@ -0,0 +1,36 @@
+/// Maximum length of a regular message content.
+pub const MESSAGE_LENGTH: usize = 2000;
+/// Maximum number of components in a single action row.
+pub const BUTTONS_PER_ROW: usize = 5;
+/// Maximum number of action rows per message.
+pub const ACTION_ROWS: usize = 5;
+/// Maximum number of options in a select menu.
+pub const SELECT_MENU_OPTIONS: usize = 25;
+/// Maximum length of button labels.
+pub const BUTTON_LABEL_LENGTH: usize = 80;
+/// Maximum length of select menu placeholders.
+pub const SELECT_PLACEHOLDER_LENGTH: usize = 150;
+/// Maximum length of select menu option labels and descriptions.
+pub const SELECT_OPTION_LENGTH: usize = 100;
+/// Maximum length of component custom ids.
+pub const CUSTOM_ID_LENGTH: usize = 100;
+/// Maximum length of channel topics.
+pub const CHANNEL_TOPIC_LENGTH: usize = 1024;
+/// Maximum length of autocomplete choices.
+pub const AUTOCOMPLETE_CHOICE_LENGTH: usize = 100;
+/// Maximum length of embed titles and field names.
+pub const EMBED_TITLE_LENGTH: usize = 256;
+/// Maximum length of embed field values.
+pub const EMBED_FIELD_LENGTH: usize = 1024;
+/// Maximum length of embed descriptions.
+pub const EMBED_DESCRIPTION_LENGTH: usize = 4096;
+/// Maximum number of characters of all texts within an embed combined.
+pub const EMBED_LENGTH: usize = 6000;
+/// Maximum length of embed footers.
+pub const EMBED_FOOTER_LENGTH: usize = 2048;
+/// Maximum number of fields per embed.
+pub const EMBED_FIELDS: usize = 25;
+/// Maximum number of embeds per message, which is the same as for attachments.
+pub const EMBEDS_PER_MESSAGE: usize = 10;
+/// Maximum size of all attachments of a message in bytes, for servers without boosts.
+pub const ATTACHMENT_SIZE: usize = 10 * 1024 * 1024;
@@ -1,15 +1,19 @@
+mod check;
 mod config;
+mod database;
+mod limits;
 mod moderation;
 mod ophase;
 mod self_management;
 mod toc;
 
-use std::{
-	fs::File,
-	io::Read,
+use std::sync::{
+	Arc,
+	RwLock,
 };
 
 use config::Config;
+use database::Database;
 use env_logger::Target;
 #[allow(unused_imports)]
 use log::{
@@ -33,12 +37,47 @@ use serenity::all::{
 	TeamMemberRole,
 	User,
 };
+use tokio::sync::Notify;
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, AppState, Error>;
 
+/// Shared state of the bot. Cloning is cheap and yields a handle to the same state, which allows background tasks to
+/// access it.
+#[derive(Clone)]
 pub struct AppState {
-	config: Config,
+	config_path: Arc<String>,
+	config: Arc<RwLock<Arc<Config>>>,
+	db: Arc<Database>,
+	/// Wakes the background task which sorts the self-managed channels.
+	sort_requests: Arc<Notify>,
+	/// Pending edits of self-managed channels, waiting for the rate limit.
+	edit_queue: Arc<self_management::EditQueue>,
+	/// Recently uploaded attachments of toc entries.
+	attachment_cache: Arc<toc::AttachmentCache>,
+}
+
+impl AppState {
+	/// Returns the currently active configuration. The returned snapshot is not affected by later reloads.
+	pub fn config(&self) -> Arc<Config> {
+		self.config.read().expect("config lock poisoned").clone()
+	}
+
+	/// Reads the configuration file again and replaces the active configuration, but only if the new one is valid.
+	fn reload_config(&self) -> Result<(), Error> {
+		let config = Config::load(self.config_path.as_str())?;
+
+		if config.bot_token != self.config().bot_token {
+			warn!("Bot token was changed in configuration, restart is required for it to take effect");
+		}
+		if config.database != self.config().database {
+			warn!("Database was changed in configuration, restart is required for it to take effect");
+		}
+
+		*self.config.write().expect("config lock poisoned") = Arc::new(config);
+		self.attachment_cache.clear();
+		Ok(())
+	}
 }
 
 /// Show this help menu
@@ -122,6 +161,31 @@ async fn register(ctx: Context<'_>, #[flag] global: bool) -> Result<(), Error> {
 	Ok(())
 }
 
+/// Lädt die Konfiguration und alle referenzierten Dateien neu. Kann nur vom Besitzer ausgeführt werden.
+#[poise::command(prefix_command, hide_in_help)]
+async fn reload(ctx: Context<'_>) -> Result<(), Error> {
+	let is_bot_owner = is_bot_team_admin_or_owner(ctx, ctx.author()).await?;
+	if !is_bot_owner {
+		ctx.say("Can only be used by bot owner").await?;
+		return Ok(());
+	}
+
+	match ctx.data().reload_config() {
+		Ok(()) => {
+			info!("Configuration reloaded by {} ({})", ctx.author().name, ctx.author().id);
+			ctx.say("Configuration reloaded!").await?;
+		},
+		Err(err) => {
+			warn!("Failed to reload configuration: {}", err);
+			ctx
+				.say(format!("Failed to reload configuration, keeping previous one: {}", err))
+				.await?;
+		},
+	}
+
+	Ok(())
+}
+
 /// Generic listener on top of poise to handle all incoming discord events. Especially button interactions, which pose
 /// doesn't support yet.
 async fn listener<'a>(ctx: &'a poise::serenity_prelude::Context, ev: &'a FullEvent, app: &'a AppState) -> Result<(), Error> {
@@ -132,16 +196,25 @@ async fn listener<'a>(ctx: &'a poise::serenity_prelude::Context, ev: &'a FullEve
 		} => {
 			if let Interaction::Component(component_interaction) = &interaction {
 				let custom_id = component_interaction.data.custom_id.as_str();
-				if custom_id.starts_with("toc:") {
+				if custom_id.starts_with("toc:") || custom_id == "toc_menu" {
 					toc::handle_toc_click(ctx, app, component_interaction).await?;
 				} else if custom_id.starts_with("assignments") {
 					toc::print_assignments(ctx, app, component_interaction).await?;
 				} else if custom_id.starts_with("assign:") {
 					toc::handle_assign_click(ctx, app, component_interaction).await?;
+				} else if custom_id.starts_with("claim_keep:") {
+					self_management::handle_claim_keep_click(ctx, app, component_interaction).await?;
 				}
 			};
 			trace!("Incoming interaction: {:?}", interaction)
 		},
+		FullEvent::ChannelDelete {
+			channel, ..
+		} => self_management::handle_channel_delete(app, channel)?,
+		FullEvent::VoiceStateUpdate {
+			old,
+			new,
+		} => self_management::handle_voice_state_update(ctx, app, old.as_ref(), new).await?,
 		FullEvent::Ready {
 			data_about_bot,
 		} => info!("Bot is ready: {:?}", data_about_bot),
@@ -190,15 +263,37 @@ async fn main() {
 		.init();
 
 	let args = std::env::args().collect::<Vec<_>>();
-	let file = args.get(1).expect("No config file given");
-	let mut file = File::open(file).unwrap();
-	let mut content = String::new();
-	file.read_to_string(&mut content).unwrap();
-	let config = toml::from_str::<Config>(content.as_str()).unwrap();
+	if args.get(1).map(String::as_str) == Some("check") {
+		let Some(config_path) = args.get(2) else {
+			error!("Usage: {} check <config>", args[0]);
+			std::process::exit(1);
+		};
+		std::process::exit(check::run(config_path));
+	}
+
+	let Some(config_path) = args.get(1).cloned() else {
+		error!("No config file given");
+		std::process::exit(1);
+	};
+	let config = match Config::load(&config_path) {
+		Ok(config) => config,
+		Err(err) => {
+			error!("Failed to load configuration: {}", err);
+			std::process::exit(1);
+		},
+	};
+
+	let db = match Database::open(&config.database) {
+		Ok(db) => db,
+		Err(err) => {
+			error!("Failed to open database: {}", err);
+			std::process::exit(1);
+		},
+	};
 
 	info!("This is a log message and we need it!");
 
-	let mut commands: Vec<_> = vec![help(), register()];
+	let mut commands: Vec<_> = vec![help(), register(), reload()];
 
 	toc::register_commands(&mut commands);
 	self_management::register_commands(&mut commands);
@@ -229,11 +324,24 @@ async fn main() {
 	let bot_token = config.bot_token.clone();
 
 	let framework = Framework::builder()
-		.setup(move |_ctx, _ready, _framework| {
+		.setup(move |ctx, _ready, _framework| {
 			Box::pin(async move {
-				Ok(AppState {
-					config,
-				})
+				let app = AppState {
+					config_path: Arc::new(config_path),
+					config: Arc::new(RwLock::new(Arc::new(config))),
+					db: Arc::new(db),
+					sort_requests: Arc::new(Notify::new()),
+					edit_queue: Arc::default(),
+					attachment_cache: Arc::default(),
+				};
+
+				if let Err(err) = self_management::import_legacy_ownership(ctx, &app).await {
+					error!("Failed to import legacy channel ownership: {}", err);
+				}
+
+				self_management::spawn_background_tasks(ctx, &app);
+
+				Ok(app)
 			})
 		})
 		.options(options)
@@ -244,7 +352,8 @@ async fn main() {
 		GatewayIntents::GUILDS
 			| GatewayIntents::GUILD_MESSAGES
 			| GatewayIntents::DIRECT_MESSAGES
-			| GatewayIntents::GUILD_INTEGRATIONS,
+			| GatewayIntents::GUILD_INTEGRATIONS
+			| GatewayIntents::GUILD_VOICE_STATES,
 	)
 	.framework(framework)
 	.await;
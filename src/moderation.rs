@@ -10,6 +10,7 @@ use serenity::{
 };
 
 use crate::{
+	database,
 	AppState,
 	Context,
 	Error,
@@ -42,7 +43,7 @@ async fn report_message(
 		Context::Application(ctx) => ctx,
 		Context::Prefix(_) => unreachable!("This command is only available as a context menu command"),
 	};
-	let report_channel = match app_context.data().config.moderation.report_channel {
+	let report_channel = match app_context.data().config().moderation.report_channel {
 		Some(id) => ChannelId::new(id),
 		None => {
 			ctx
@@ -55,7 +56,7 @@ async fn report_message(
 	let report = poise::execute_modal::<_, _, ModalReport>(app_context, None, None).await?;
 
 	match report {
-		Some(_) => {
+		Some(report) => {
 			let message_abbreviation = if msg.content.len() > REPORT_MESSAGE_LENGTH {
 				&msg.content[..REPORT_MESSAGE_LENGTH]
 			} else {
@@ -65,7 +66,7 @@ async fn report_message(
 			let embed = CreateEmbed::new()
 				.title(format!("Neue Meldung von {}", ctx.author().name))
 				.description(message_abbreviation)
-				.field("Grund", report.unwrap().reason, true)
+				.field("Grund", &report.reason, true)
 				.field("Link", format!("[Link]({})", msg.link()), true)
 				.field("Autor", msg.author.mention().to_string(), true)
 				.field("Kanal", msg.channel_id.mention().to_string(), true)
@@ -76,6 +77,21 @@ async fn report_message(
 				.send_message(ctx, CreateMessage::default().embed(embed))
 				.await?;
 
+			// keep track of reports, so repeated offenders can be identified later on
+			ctx.data().db.lock().execute(
+				"INSERT INTO reports (message_id, channel_id, author_id, reporter_id, reason, content, created_at) VALUES (?1, ?2, ?3, \
+				 ?4, ?5, ?6, ?7)",
+				(
+					msg.id.get() as i64,
+					msg.channel_id.get() as i64,
+					msg.author.id.get() as i64,
+					ctx.author().id.get() as i64,
+					&report.reason,
+					&msg.content,
+					database::now(),
+				),
+			)?;
+
 			ctx
 				.send(CreateReply::default().content("Die Nachricht wurde gemeldet."))
 				.await?;
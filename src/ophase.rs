@@ -16,6 +16,7 @@ use serenity::all::{
 
 use crate::{
 	config::OPhase,
+	database,
 	AppState,
 	Error,
 };
@@ -59,7 +60,8 @@ async fn ersti(ctx: poise::ApplicationContext<'_, AppState, Error>) -> Result<()
 		return Err("Dieser Befehl kann nicht in DMs ausgeführt werden".into());
 	};
 
-	let Some(config) = &ctx.data.config.o_phase else {
+	let config = ctx.data.config();
+	let Some(config) = &config.o_phase else {
 		return Err("O-Phase Funktionalität ist nicht konfiguriert".into());
 	};
 	let role_id = get_role_id(ctx, config).await?;
@@ -70,7 +72,13 @@ async fn ersti(ctx: poise::ApplicationContext<'_, AppState, Error>) -> Result<()
 		return Ok(());
 	};
 
-	if response.password.to_lowercase() != config.password.to_lowercase() {
+	let success = response.password.to_lowercase() == config.password.to_lowercase();
+	ctx.data.db.lock().execute(
+		"INSERT INTO ophase_attempts (user_id, success, created_at) VALUES (?1, ?2, ?3)",
+		(ctx.author().id.get() as i64, success, database::now()),
+	)?;
+
+	if !success {
 		info!(
 			"Falsches Passwort '{}': {} ({})",
 			response.password,
@@ -1,6 +1,18 @@
-use std::time::{
-	Duration,
-	SystemTime,
+mod archive;
+mod audit;
+mod claim;
+mod edit_queue;
+mod mutes;
+mod names;
+mod ownership;
+mod privacy;
+mod sorting;
+mod sweeper;
+mod voice;
+
+use std::{
+	collections::HashMap,
+	time::Duration,
 };
 
 #[allow(unused_imports)]
@@ -14,13 +26,19 @@ use log::{
 use poise::{
 	serenity_prelude::{
 		ChannelId,
+		ChannelType,
 		CreateEmbed,
+		CreateEmbedFooter,
 		GuildChannel,
 		GuildId,
+		Member,
 		Mention,
 		Mentionable,
+		Message,
+		MessageId,
 		User,
 		UserId,
+		VoiceState,
 	},
 	Command,
 	CreateReply,
@@ -31,257 +49,1804 @@ use serde::{
 };
 use serenity::{
 	all::{
+		ButtonStyle,
+		CacheHttp,
+		ComponentInteraction,
+		ComponentInteractionCollector,
+		CreateActionRow,
+		CreateButton,
 		CreateChannel,
+		CreateInteractionResponse,
+		CreateInteractionResponseMessage,
 		CreateMessage,
+		ErrorResponse,
+		HttpError,
+		PermissionOverwrite,
+		PermissionOverwriteType,
+		Permissions,
+		Role,
+		RoleId,
+		StatusCode,
 	},
 	builder::EditChannel,
 };
 
+pub use self::edit_queue::EditQueue;
+use self::{
+	archive::ArchivedChannel,
+	audit::{
+		AuditEvent,
+		ChannelSnapshot,
+	},
+	claim::PendingClaim,
+	edit_queue::ChannelEdit,
+	mutes::ChannelMute,
+	ownership::ChannelOwnership,
+	sorting::PinnedChannel,
+	sweeper::InactivityWarning,
+	voice::TemporaryRoom,
+};
 use crate::{
-	config::SelfManagement,
+	config::{
+		Archive,
+		SelfManagement,
+		SortStrategy,
+	},
+	database::{
+		self,
+		Database,
+	},
+	limits::EMBED_FIELD_LENGTH,
 	AppState,
 	Context,
 	Error,
 };
 
-const CHANNEL_EDIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum number of channels Discord allows in a single category.
+const CATEGORY_CHANNEL_LIMIT: usize = 50;
+/// Stays below the 15 minutes an interaction token is valid, so the request can still be edited afterwards.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(14 * 60);
+const LIST_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const LIST_PAGE_SIZE: usize = 10;
+/// Audit events are verbose, so fewer of them fit on a page.
+const AUDIT_PAGE_SIZE: usize = 5;
+/// Descriptions are shortened in the channel list, so a full page fits into a single embed.
+const LIST_TOPIC_LENGTH: usize = 200;
+const BACKGROUND_TASK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Permissions which are revoked from everyone in archived channels.
+const ARCHIVE_LOCKED_PERMISSIONS: Permissions = Permissions::SEND_MESSAGES
+	.union(Permissions::SEND_MESSAGES_IN_THREADS)
+	.union(Permissions::CREATE_PUBLIC_THREADS)
+	.union(Permissions::CREATE_PRIVATE_THREADS)
+	.union(Permissions::ADD_REACTIONS);
+/// Permissions which are revoked from users muted in a channel.
+const MUTED_PERMISSIONS: Permissions = ARCHIVE_LOCKED_PERMISSIONS;
 
 pub fn register_commands(commands: &mut Vec<Command<AppState, Error>>) {
 	commands.push(channel_dummy());
+	commands.push(audit_dummy());
+}
+
+/// Enthält Befehle für Moderatoren, um die Geschichte von Kanälen nachzuvollziehen.
+#[poise::command(
+	slash_command,
+	rename = "audit",
+	subcommands("audit_channel"),
+	default_member_permissions = "MANAGE_CHANNELS"
+)]
+async fn audit_dummy(_ctx: Context<'_>) -> Result<(), Error> {
+	unreachable!() // Upper commands can never be called from discord, all good.
+}
+
+/// Zeigt alle Aktionen an, die einen selbstverwalteten Kanal betroffen haben. Nur für Moderatoren.
+///
+/// Der Kanal wird über seine ID angegeben, damit auch gelöschte Kanäle nachvollzogen werden können.
+#[poise::command(slash_command, rename = "kanal")]
+async fn audit_channel(
+	ctx: Context<'_>,
+	#[description = "ID oder Erwähnung des Channels, auch von gelöschten."] kanal: String,
+) -> Result<(), Error> {
+	if !is_channel_admin(&ctx).await {
+		return Err(Error::from("Nur Moderatoren dürfen die Geschichte von Kanälen einsehen."));
+	}
+
+	// accept mentions like `<#123>` as well, since they are what users get when copying a channel
+	let channel_id = kanal
+		.trim()
+		.trim_start_matches("<#")
+		.trim_end_matches('>')
+		.parse::<u64>()
+		.ok()
+		.filter(|id| *id != 0)
+		.map(ChannelId::new)
+		.ok_or_else(|| Error::from(format!("`{}` ist keine gültige Kanal-ID.", kanal)))?;
+
+	let events = AuditEvent::for_channel(&ctx.data().db, channel_id)?;
+	if events.is_empty() {
+		return Err(Error::from(format!("Zu {} gibt es keine Einträge.", channel_id.mention())));
+	}
+
+	// deleted channels can't be mentioned anymore, so their last known name is shown instead
+	let name = events
+		.iter()
+		.find_map(|event| event.after.as_ref().or(event.before.as_ref()))
+		.map(|snapshot| format!("#{}", snapshot.name))
+		.unwrap_or_else(|| channel_id.to_string());
+
+	let entries = events
+		.iter()
+		.map(|event| {
+			let (name, mut value) = event.describe();
+			if value.chars().count() > EMBED_FIELD_LENGTH {
+				value = value.chars().take(EMBED_FIELD_LENGTH - 1).collect::<String>() + "…";
+			}
+			(name, value)
+		})
+		.collect::<Vec<_>>();
+	send_paginated(ctx, &format!("Geschichte von {}", name), &entries, AUDIT_PAGE_SIZE).await
+}
+
+/// Enthält Befehle für den selbstverwalteten Bereich des Servers.
+#[poise::command(
+	slash_command,
+	rename = "kanal",
+	subcommands(
+		"create_channel",
+		"update_channel",
+		"delete_channel",
+		"claim_channel",
+		"add_co_owner",
+		"remove_co_owner",
+		"transfer_channel",
+		"restore_channel",
+		"pin_channel",
+		"unpin_channel",
+		"list_channels",
+		"channel_info",
+		"set_private",
+		"add_member",
+		"remove_member",
+		"restrict_to_role",
+		"set_slowmode",
+		"pin_message",
+		"unpin_message",
+		"mute_user",
+		"unmute_user"
+	)
+)]
+async fn channel_dummy(_ctx: Context<'_>) -> Result<(), Error> {
+	unreachable!() // Upper commands can never be called from discord, all good.
+}
+
+/// Limits which apply to a member when creating channels, depending on their roles.
+struct MemberPolicy {
+	limit: u64,
+	join_age_limit: i64,
+}
+
+impl MemberPolicy {
+	/// Returns `None` if the member may not create channels at all. Denying roles win over all others, otherwise the
+	/// most generous limit and join age of all matching policies apply, even if they stem from different policies.
+	fn for_member(member: &Member, config: &SelfManagement) -> Option<Self> {
+		let policies = config
+			.policies
+			.iter()
+			.filter(|policy| member.roles.contains(&RoleId::new(policy.role)))
+			.collect::<Vec<_>>();
+		if policies.iter().any(|policy| policy.deny) {
+			return None;
+		}
+
+		Some(MemberPolicy {
+			limit: policies
+				.iter()
+				.map(|policy| policy.limit.unwrap_or(config.limit))
+				.max()
+				.unwrap_or(config.limit),
+			join_age_limit: policies
+				.iter()
+				.map(|policy| policy.join_age_limit.unwrap_or(config.join_age_limit))
+				.min()
+				.unwrap_or(config.join_age_limit),
+		})
+	}
+
+	/// Checks whether the member may own one more channel, which applies to creating, receiving and restoring channels
+	/// alike. Returns the reason if not.
+	async fn check(http: impl CacheHttp, app: &AppState, member: &Member) -> Result<Option<PolicyViolation>, Error> {
+		let config = app.config();
+		let Some(policy) = MemberPolicy::for_member(member, &config.self_managment) else {
+			return Ok(Some(PolicyViolation::Denied));
+		};
+
+		// members without known join date are treated as new, rather than letting them bypass the limit
+		let joined_at = member.joined_at.map_or(i64::MAX, |joined_at| joined_at.timestamp());
+		if database::now().saturating_sub(joined_at) < policy.join_age_limit {
+			return Ok(Some(PolicyViolation::JoinedRecently));
+		}
+
+		let owned = owned_channels(http, app, member.guild_id, member.user.id).await?;
+		trace!(
+			"User {} owns {} channels: {:?}",
+			member.user.id,
+			owned.len(),
+			owned.iter().map(|c| c.name.clone()).collect::<Vec<_>>()
+		);
+		if owned.len() >= policy.limit as usize {
+			return Ok(Some(PolicyViolation::LimitReached(policy.limit)));
+		}
+
+		Ok(None)
+	}
+}
+
+/// Reason why a member may not own another channel.
+enum PolicyViolation {
+	Denied,
+	JoinedRecently,
+	LimitReached(u64),
+}
+
+impl PolicyViolation {
+	/// Explanation addressed to the member themselves.
+	fn to_author(&self) -> String {
+		match self {
+			PolicyViolation::Denied => "Du darfst derzeit keine Kanäle besitzen.".to_string(),
+			PolicyViolation::JoinedRecently => "Du bist noch nicht lange genug auf dem Server, um einen Kanal zu besitzen.".to_string(),
+			PolicyViolation::LimitReached(limit) => format!("Du darfst nur maximal {} Kanäle besitzen.", limit),
+		}
+	}
+
+	/// Explanation about another member, like the recipient of a transfer.
+	fn about(&self, member: &Member) -> String {
+		match self {
+			PolicyViolation::Denied => format!("{} darf derzeit keine Kanäle besitzen.", member.mention()),
+			PolicyViolation::JoinedRecently => format!(
+				"{} ist noch nicht lange genug auf dem Server, um einen Kanal zu besitzen.",
+				member.mention()
+			),
+			PolicyViolation::LimitReached(limit) => format!(
+				"{} besitzt bereits die maximale Anzahl von {} Kanälen.",
+				member.mention(),
+				limit
+			),
+		}
+	}
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum ChannelKind {
+	#[name = "Text"]
+	Text,
+	#[name = "Sprache"]
+	Voice,
+	#[name = "Bühne"]
+	Stage,
+}
+
+/// Erstellt einen neuen Kanal.
+#[poise::command(slash_command, rename = "erstellen")]
+async fn create_channel(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] name: String,
+	#[description = "Wofür ist dieser Channel?"] beschreibung: Option<String>,
+	#[description = "Die Art des Channels, standardmäßig ein Textkanal."] art: Option<ChannelKind>,
+	#[description = "Eine Vorlage mit Voreinstellungen für den Channel."]
+	#[autocomplete = "autocomplete_template"]
+	vorlage: Option<String>,
+) -> Result<(), Error> {
+	let app = ctx.data();
+	let config = app.config();
+	let sm = &config.self_managment;
+
+	let template = match &vorlage {
+		Some(vorlage) => Some(
+			sm.templates
+				.get(vorlage)
+				.ok_or_else(|| format!("Die Vorlage `{}` gibt es nicht.", vorlage))?,
+		),
+		None => None,
+	};
+	let beschreibung = beschreibung
+		.or_else(|| template.and_then(|template| template.topic.clone()))
+		.ok_or("Bitte gib eine Beschreibung an.")?;
+
+	let guild_id = ctx
+		.guild_id()
+		.ok_or("Dieser Befehl kann nur auf einem Server ausgeführt werden.")?;
+
+	let member = ctx
+		.author_member()
+		.await
+		.ok_or("Ich konnte deine Mitgliedschaft leider nicht abfragen.")?;
+	if let Some(violation) = MemberPolicy::check(ctx, app, &member).await? {
+		return Err(Error::from(violation.to_author()));
+	}
+
+	check_channel_name(&ctx, guild_id, &name, None).await?;
+	let category = category_with_room(&guild_id.channels(ctx).await?, sm)?;
+
+	// create channel in category (will fail if in different guild)
+	ctx.defer_ephemeral().await?;
+	// only text channels have a topic, so the description of other channels merely ends up in the log
+	let create_channel = match art.unwrap_or(ChannelKind::Text) {
+		ChannelKind::Text => CreateChannel::new(name).topic(&beschreibung),
+		ChannelKind::Voice => CreateChannel::new(name).kind(ChannelType::Voice),
+		ChannelKind::Stage => CreateChannel::new(name).kind(ChannelType::Stage),
+	};
+	let mut create_channel = create_channel.category(category);
+	if let Some(template) = template {
+		let overwrites = template.permissions.iter().map(|permission| PermissionOverwrite {
+			allow: permission.allow,
+			deny: permission.deny,
+			kind: PermissionOverwriteType::Role(RoleId::new(permission.role)),
+		});
+		create_channel = create_channel
+			.rate_limit_per_user(template.slowmode)
+			.nsfw(template.nsfw)
+			.permissions(overwrites);
+	}
+	let channel = guild_id.create_channel(ctx, create_channel).await?;
+	ChannelOwnership::new(channel.id, ctx.author().id).insert(&app.db)?;
+
+	// recent undocumented change discards any newlines in the topic during creation, so we have to set it again via edit
+	// call (costs another api call, great)
+	if channel.kind == ChannelType::Text {
+		channel.id.edit(ctx, EditChannel::default().topic(&beschreibung)).await?;
+	}
+
+	if let Some(welcome) = template.and_then(|template| template.welcome.as_ref()) {
+		if channel.kind == ChannelType::Text {
+			let message = channel
+				.send_message(ctx, CreateMessage::default().content(&welcome.content))
+				.await?;
+			message.pin(ctx).await?;
+		}
+	}
+
+	// inform user about success
+	ctx
+		.send(CreateReply::default().content(format!("Ich hab deinen Kanal erstellt: {}", channel.mention())))
+		.await?;
+
+	let mut details = match channel.kind {
+		ChannelType::Text => vec![],
+		_ => vec![("Beschreibung", beschreibung)],
+	};
+	if let Some(vorlage) = vorlage {
+		details.push(("Vorlage", vorlage));
+	}
+	log_both(&ctx, "Kanal erstellt", None, Some(&channel), &details).await?;
+
+	sorting::request_sort(app);
+
+	Ok(())
+}
+
+async fn autocomplete_template(ctx: Context<'_>, partial: &str) -> Vec<String> {
+	let partial = partial.to_lowercase();
+	ctx
+		.data()
+		.config()
+		.self_managment
+		.templates
+		.keys()
+		.filter(|name| name.to_lowercase().contains(&partial))
+		.take(25)
+		.cloned()
+		.collect()
+}
+
+/// Modifiziert den angegebenen Kanal.
+#[poise::command(slash_command, rename = "ändern")]
+async fn update_channel(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] kanal: GuildChannel,
+	#[description = "Eine neuer Name."] name: Option<String>,
+	#[description = "Eine neue Beschreibung."] beschreibung: Option<String>,
+	#[description = "Setzt den NSFW Status."] nsfw: Option<bool>,
+) -> Result<(), Error> {
+	let guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	// enforce ownership, if enabled
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	if name.is_none() && beschreibung.is_none() && nsfw.is_none() {
+		return Err(Error::from("Du hast keine Änderung angegeben."));
+	}
+	if let Some(name) = &name {
+		check_channel_name(&ctx, guild, name, Some(kanal.id)).await?;
+	}
+
+	// channel edits have absolute bonkers rate limits, so they are queued and applied once the limit allows it
+	let interaction_token = match ctx {
+		poise::Context::Application(ctx) => Some(ctx.interaction.token.clone()),
+		poise::Context::Prefix(_) => None,
+	};
+	let edit = ChannelEdit {
+		name,
+		topic: beschreibung,
+		nsfw,
+	};
+	let eta = app.edit_queue.enqueue(
+		ctx.serenity_context(),
+		app,
+		kanal.id,
+		edit,
+		ctx.author().clone(),
+		interaction_token,
+	);
+
+	let content = match eta <= database::now() {
+		true => format!(
+			"Ich übernehme deine Änderung an {} jetzt und sag dir Bescheid, sobald ich fertig bin.",
+			kanal.mention()
+		),
+		false => format!(
+			"Discord erlaubt nur wenige Änderungen an einem Kanal in kurzer Zeit. Deine Änderung an {} wird <t:{}:R> übernommen, ich \
+			 sag dir dann Bescheid.",
+			kanal.mention(),
+			eta
+		),
+	};
+	ctx.send(CreateReply::default().content(content).ephemeral(true)).await?;
+
+	Ok(())
+}
+
+/// Beansprucht den angegebenen Kanal.
+#[poise::command(slash_command, rename = "aneignen")]
+async fn claim_channel(ctx: Context<'_>, #[description = "Der Name des Channels."] kanal: GuildChannel) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	if !config.self_managment.claiming || !config.self_managment.ownership {
+		return Err(Error::from("Kanalübernahmen sind deaktiviert."));
+	}
+
+	// enforce ownership, co-owners may only take over once the owner has abandoned the channel like everyone else
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	let may_claim = match channel_access(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		ChannelAccess::Free | ChannelAccess::Owner | ChannelAccess::Abandoned => true,
+		ChannelAccess::CoOwner => is_abandoned(&app.db, &kanal, &config.self_managment)?,
+		ChannelAccess::Denied => false,
+	};
+	if !may_claim {
+		return Err(Error::from("Du darfst diesen Kanal nicht übernehmen."));
+	}
+	if PendingClaim::load(&app.db, kanal.id)?.is_some() {
+		return Err(Error::from("Für diesen Kanal läuft bereits eine Übernahme."));
+	}
+
+	// claiming must not circumvent the channel limit, refreshing the ownership of an own channel doesn't add one
+	if ownership.as_ref().is_none_or(|ownership| ownership.owner != ctx.author().id) {
+		let member = ctx
+			.author_member()
+			.await
+			.ok_or("Ich konnte deine Mitgliedschaft leider nicht abfragen.")?;
+		if let Some(violation) = MemberPolicy::check(ctx, app, &member).await? {
+			return Err(Error::from(violation.to_author()));
+		}
+	}
+	ctx.defer_ephemeral().await?;
+
+	// strangers taking over an abandoned channel have to give the owner a chance to object
+	let author = ctx.author();
+	let objection_period = config.self_managment.objection_period;
+	if let Some(ownership) = &ownership {
+		if ownership.owner != author.id && !ownership.co_owners.contains(&author.id) && objection_period > 0 {
+			let deadline = database::now() + objection_period as i64;
+			let previous_message = last_message_id(&app.db, &kanal)?;
+			let message = kanal
+				.send_message(
+					ctx,
+					CreateMessage::default()
+						.content(format!(
+							"{}, {} möchte diesen verlassenen Kanal übernehmen. Falls du Besitzer bleiben möchtest, drücke bis <t:{}:R> auf \
+							 „Behalten“.",
+							ownership.owner.mention(),
+							author.mention(),
+							deadline
+						))
+						.components(claim::keep_button(kanal.id)),
+				)
+				.await?;
+
+			PendingClaim {
+				channel: kanal.id,
+				claimant: author.id,
+				message: message.id,
+				previous_message,
+				deadline,
+			}
+			.insert(&app.db)?;
+
+			ctx
+				.send(CreateReply::default().content(format!(
+					"Ich hab den Besitzer von {} benachrichtigt. Falls bis <t:{}:R> niemand widerspricht, wirst du der neue Besitzer.",
+					kanal.mention(),
+					deadline
+				)))
+				.await?;
+			log_both(&ctx, "Kanalübernahme beantragt", None, Some(&kanal), &[(
+				"Besitzer",
+				ownership.owner.mention().to_string(),
+			)])
+			.await?;
+
+			return Ok(());
+		}
+	}
+
+	ChannelOwnership::set_owner(&app.db, kanal.id, ctx.author().id)?;
+	privacy::grant_access(ctx, &kanal, ctx.author().id).await?;
+
+	// co-owners taking over keep the previous owner around as co-owner, since they are still working together
+	if let Some(ownership) = &ownership {
+		if ownership.co_owners.contains(&ctx.author().id) {
+			ChannelOwnership::add_co_owner(&app.db, kanal.id, ownership.owner)?;
+		}
+	}
+
+	// inform user about success
+	ctx
+		.send(CreateReply::default().content(format!("Du bist nun der neue Besitzer von: {}", kanal.name())))
+		.await?;
+	log_both(
+		&ctx,
+		"Kanal übernommen",
+		None,
+		Some(&kanal),
+		&owner_change(ownership.as_ref(), ctx.author().id),
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// Überträgt den angegebenen Kanal an einen anderen Nutzer.
+#[poise::command(slash_command, rename = "übertragen")]
+async fn transfer_channel(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] kanal: GuildChannel,
+	#[description = "Der neue Besitzer."] nutzer: User,
+) -> Result<(), Error> {
+	let guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+	let sm = &config.self_managment;
+
+	if !sm.ownership {
+		return Err(Error::from("Kanalbesitz ist deaktiviert."));
+	}
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	let is_admin = is_channel_admin(&ctx).await;
+	if !is_admin && !can_manage_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), sm)? {
+		return Err(Error::from("Nur der Besitzer darf diesen Kanal übertragen."));
+	}
+	if nutzer.bot {
+		return Err(Error::from("Kanäle können nicht an Bots übertragen werden."));
+	}
+	if nutzer.id == ctx.author().id {
+		return Err(Error::from("Du kannst den Kanal nicht an dich selbst übertragen."));
+	}
+	let recipient = guild.member(ctx, nutzer.id).await?;
+	if let Some(violation) = MemberPolicy::check(ctx, app, &recipient).await? {
+		return Err(Error::from(violation.about(&recipient)));
+	}
+
+	// recipient has to agree, otherwise people could dump channels on others
+	let accept_id = format!("{}transfer_accept", ctx.id());
+	let decline_id = format!("{}transfer_decline", ctx.id());
+	let reply = ctx
+		.send(
+			CreateReply::default()
+				.content(format!(
+					"{}, {} möchte dir den Kanal {} übertragen.",
+					nutzer.mention(),
+					ctx.author().mention(),
+					kanal.mention()
+				))
+				.components(vec![CreateActionRow::Buttons(vec![
+					CreateButton::new(&accept_id).label("Annehmen").style(ButtonStyle::Success),
+					CreateButton::new(&decline_id).label("Ablehnen").style(ButtonStyle::Danger),
+				])]),
+		)
+		.await?;
+
+	let interaction = {
+		let accept_id = accept_id.clone();
+		ComponentInteractionCollector::new(ctx)
+			.author_id(nutzer.id)
+			.timeout(TRANSFER_TIMEOUT)
+			.filter(move |i| i.data.custom_id == accept_id || i.data.custom_id == decline_id)
+			.await
+	};
+	let Some(interaction) = interaction else {
+		reply
+			.edit(
+				ctx,
+				CreateReply::default()
+					.content(format!("Die Übertragung von {} ist abgelaufen.", kanal.mention()))
+					.components(vec![]),
+			)
+			.await?;
+		return Ok(());
+	};
+
+	// ownership might have changed while waiting for the recipient, who might have created channels in the meantime
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	let violation = MemberPolicy::check(ctx, app, &recipient).await?;
+	let content = if interaction.data.custom_id != accept_id {
+		format!("{} hat die Übertragung von {} abgelehnt.", nutzer.mention(), kanal.mention())
+	} else if !is_admin
+		&& !can_manage_channel(
+			&app.db,
+			&ctx.author().id,
+			&kanal,
+			ownership.as_ref(),
+			&app.config().self_managment,
+		)? {
+		format!(
+			"{} darf {} nicht mehr übertragen, die Übertragung ist fehlgeschlagen.",
+			ctx.author().mention(),
+			kanal.mention()
+		)
+	} else if let Some(violation) = violation {
+		format!(
+			"{} Die Übertragung von {} ist fehlgeschlagen.",
+			violation.about(&recipient),
+			kanal.mention()
+		)
+	} else {
+		ChannelOwnership::set_owner(&app.db, kanal.id, nutzer.id)?;
+		privacy::grant_access(ctx, &kanal, nutzer.id).await?;
+		log_both(
+			&ctx,
+			"Kanal übertragen",
+			None,
+			Some(&kanal),
+			&owner_change(ownership.as_ref(), nutzer.id),
+		)
+		.await?;
+		format!("{} ist nun der neue Besitzer von {}.", nutzer.mention(), kanal.mention())
+	};
+
+	interaction
+		.create_response(
+			ctx,
+			CreateInteractionResponse::UpdateMessage(CreateInteractionResponseMessage::new().content(content).components(vec![])),
+		)
+		.await?;
+
+	Ok(())
+}
+
+/// Löscht den angegebenen Kanal.
+#[poise::command(slash_command, rename = "löschen")]
+async fn delete_channel(
+	ctx: Context<'_>,
+	#[description = "Der Channel, den du löschen möchtest."] kanal: GuildChannel,
+) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	// enforce ownership, if enabled
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_manage_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)?
+		&& !is_channel_admin(&ctx).await
+	{
+		return Err(Error::from("Du darfst diesen Kanal nicht löschen."));
+	}
+
+	ctx.defer_ephemeral().await?;
+
+	// archive channel instead, if enabled
+	if let Some(archive) = &config.self_managment.archive {
+		archive_channel(ctx, &app.db, &kanal, archive, ctx.author().id).await?;
+		ctx
+			.send(CreateReply::default().content(format!(
+				"Ich hab den Kanal archiviert: {}. Er wird <t:{}:R> endgültig gelöscht.",
+				kanal.mention(),
+				database::now() + archive.retention as i64
+			)))
+			.await?;
+		log_both(&ctx, "Kanal archiviert", Some(&kanal), None, &[]).await?;
+		return Ok(());
+	}
+
+	// perform deletion
+	kanal.delete(ctx).await?;
+	ChannelOwnership::delete(&app.db, kanal.id)?;
+
+	// inform user about success (only possible if command was not issued from the very same channel)
+	if ctx.channel_id() != kanal.id {
+		ctx
+			.send(CreateReply::default().content(format!("Ich hab den Kanal gelöscht: {}", kanal.name())))
+			.await?;
+	}
+
+	log_both(&ctx, "Kanal gelöscht", Some(&kanal), None, &[]).await?;
+
+	Ok(())
+}
+
+/// Stellt einen archivierten Kanal wieder her.
+#[poise::command(slash_command, rename = "wiederherstellen")]
+async fn restore_channel(ctx: Context<'_>, #[description = "Der archivierte Channel."] kanal: GuildChannel) -> Result<(), Error> {
+	let app = ctx.data();
+	let config = app.config();
+
+	let guild = ctx
+		.guild_id()
+		.ok_or("Dieser Befehl kann nur in einem Server ausgeführt werden.")?;
+	if kanal.guild_id != guild {
+		return Err(Error::from("Dieser Channel ist nicht von diesem Server"));
+	}
+	let archived = ArchivedChannel::load(&app.db, kanal.id)?.ok_or("Dieser Kanal ist nicht archiviert.")?;
+
+	// only owners and server moderators may restore channels
+	let is_admin = is_channel_admin(&ctx).await;
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	let is_owner = match &ownership {
+		Some(ownership) => ownership.owner == ctx.author().id,
+		None => false,
+	};
+	if !is_admin && !is_owner && config.self_managment.ownership {
+		return Err(Error::from("Du darfst diesen Kanal nicht wiederherstellen."));
+	}
+
+	// restoring must not circumvent the channel limit, which only non-moderators restoring their own channel are subject to
+	if !is_admin && is_owner {
+		let member = ctx
+			.author_member()
+			.await
+			.ok_or("Ich konnte deine Mitgliedschaft leider nicht abfragen.")?;
+		if let Some(violation) = MemberPolicy::check(ctx, app, &member).await? {
+			return Err(Error::from(violation.to_author()));
+		}
+	}
+
+	// original category might have been filled up or removed from the config in the meantime
+	let channels = guild.channels(ctx).await?;
+	let original_has_room = config.self_managment.category.contains(&archived.category.get())
+		&& channels.values().filter(|c| c.parent_id == Some(archived.category)).count() < CATEGORY_CHANNEL_LIMIT;
+	let category = match original_has_room {
+		true => archived.category,
+		false => category_with_room(&channels, &config.self_managment)?,
+	};
+
+	// mutes which expired during the archival have already been lifted, so they must not come back with the overwrites
+	let mut permission_overwrites = Vec::new();
+	for mut overwrite in archived.permission_overwrites {
+		if let PermissionOverwriteType::Member(user) = overwrite.kind {
+			if !ChannelMute::is_muted(&app.db, kanal.id, user)? {
+				overwrite.deny -= MUTED_PERMISSIONS;
+				if overwrite.allow.is_empty() && overwrite.deny.is_empty() {
+					continue;
+				}
+			}
+		}
+		permission_overwrites.push(overwrite);
+	}
+
+	ctx.defer_ephemeral().await?;
+	let after = kanal
+		.id
+		.edit(
+			ctx,
+			EditChannel::default().category(category).permissions(permission_overwrites),
+		)
+		.await?;
+	ArchivedChannel::delete(&app.db, kanal.id)?;
+
+	ctx
+		.send(CreateReply::default().content(format!("Ich hab den Kanal wiederhergestellt: {}", kanal.mention())))
+		.await?;
+	log_both(&ctx, "Kanal wiederhergestellt", None, Some(&after), &[]).await?;
+
+	sorting::request_sort(app);
+
+	Ok(())
+}
+
+/// Heftet einen Kanal an, sodass er bei der Sortierung vorne steht. Nur für Moderatoren.
+#[poise::command(slash_command, rename = "anheften")]
+async fn pin_channel(ctx: Context<'_>, #[description = "Der Name des Channels."] kanal: GuildChannel) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	if !is_channel_admin(&ctx).await {
+		return Err(Error::from("Nur Moderatoren dürfen Kanäle anheften."));
+	}
+
+	let pinned = PinnedChannel {
+		channel: kanal.id,
+		pinned_by: ctx.author().id,
+		pinned_at: database::now(),
+	};
+	if !pinned.insert(&app.db)? {
+		return Err(Error::from(format!("{} ist bereits angeheftet.", kanal.name())));
+	}
+
+	let mut content = format!("{} ist nun angeheftet.", kanal.mention());
+	if config.self_managment.sorting != SortStrategy::PinnedFirst {
+		content += " Die aktuelle Sortierung berücksichtigt angeheftete Kanäle allerdings nicht.";
+	}
+	ctx.send(CreateReply::default().content(content).ephemeral(true)).await?;
+	log_both(&ctx, "Kanal angeheftet", None, Some(&kanal), &[]).await?;
+
+	sorting::request_sort(app);
+
+	Ok(())
+}
+
+/// Löst einen angehefteten Kanal wieder. Nur für Moderatoren.
+#[poise::command(slash_command, rename = "loslösen")]
+async fn unpin_channel(ctx: Context<'_>, #[description = "Der Name des Channels."] kanal: GuildChannel) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+
+	if !is_channel_admin(&ctx).await {
+		return Err(Error::from("Nur Moderatoren dürfen Kanäle loslösen."));
+	}
+
+	if !PinnedChannel::delete(&app.db, kanal.id)? {
+		return Err(Error::from(format!("{} ist nicht angeheftet.", kanal.name())));
+	}
+
+	ctx
+		.send(
+			CreateReply::default()
+				.content(format!("{} ist nicht mehr angeheftet.", kanal.mention()))
+				.ephemeral(true),
+		)
+		.await?;
+	log_both(&ctx, "Kanal losgelöst", None, Some(&kanal), &[]).await?;
+
+	sorting::request_sort(app);
+
+	Ok(())
+}
+
+/// Listet alle selbstverwalteten Kanäle auf.
+#[poise::command(slash_command, rename = "liste")]
+async fn list_channels(ctx: Context<'_>) -> Result<(), Error> {
+	let app = ctx.data();
+	let config = app.config();
+	let sm = &config.self_managment;
+
+	let guild = ctx
+		.guild_id()
+		.ok_or("Dieser Befehl kann nur auf einem Server ausgeführt werden.")?;
+	let mut channels = guild
+		.channels(ctx)
+		.await?
+		.into_values()
+		.filter(|c| is_self_managed(c, sm))
+		.collect::<Vec<_>>();
+	if channels.is_empty() {
+		return Err(Error::from("Es gibt noch keine selbstverwalteten Kanäle."));
+	}
+
+	// same order as in the channel list
+	let category_index = |c: &GuildChannel| c.parent_id.and_then(|id| sm.category.iter().position(|c| *c == id.get()));
+	channels.sort_by_key(|c| (category_index(c), c.position, c.id));
+
+	let entries = channels
+		.iter()
+		.map(|channel| {
+			let ownership = ChannelOwnership::load(&app.db, channel.id)?;
+			list_entry(&app.db, channel, ownership.as_ref(), sm)
+		})
+		.collect::<Result<Vec<_>, Error>>()?;
+	send_paginated(ctx, "Selbstverwaltete Kanäle", &entries, LIST_PAGE_SIZE).await
+}
+
+/// Zeigt Informationen zu einem selbstverwalteten Kanal an.
+#[poise::command(slash_command, rename = "info")]
+async fn channel_info(ctx: Context<'_>, #[description = "Der Name des Channels."] kanal: GuildChannel) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+	let sm = &config.self_managment;
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	let created_at = match &ownership {
+		Some(ownership) => ownership.created_at,
+		None => kanal.id.created_at().timestamp(),
+	};
+
+	let mut embed = CreateEmbed::new()
+		.title(&kanal.name)
+		.description(topic_or_placeholder(&kanal))
+		.field("Erstellt", format!("<t:{}:D>", created_at), true)
+		.field("Letzte Aktivität", format!("<t:{}:R>", last_activity(&app.db, &kanal)?), true)
+		.field(
+			"Status",
+			if is_abandoned(&app.db, &kanal, sm)? {
+				"verlassen"
+			} else {
+				"aktiv"
+			},
+			true,
+		);
+	if let Some(ownership) = &ownership {
+		embed = embed.field("Besitzer", ownership.owner.mention().to_string(), true).field(
+			"Ersteller",
+			ownership.creator.mention().to_string(),
+			true,
+		);
+		if !ownership.co_owners.is_empty() {
+			let co_owners = ownership
+				.co_owners
+				.iter()
+				.map(|id| id.mention().to_string())
+				.collect::<Vec<_>>();
+			embed = embed.field("Mitbesitzer", co_owners.join(", "), true);
+		}
+	}
+
+	// tell the caller what they could do with the channel
+	let access = match channel_access(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), sm)? {
+		ChannelAccess::Owner => "Du bist Besitzer dieses Kanals.",
+		ChannelAccess::CoOwner => "Du bist Mitbesitzer dieses Kanals.",
+		ChannelAccess::Free => "Jeder darf diesen Kanal bearbeiten.",
+		ChannelAccess::Abandoned if !sm.claiming => "Dieser Kanal ist verlassen, Übernahmen sind allerdings deaktiviert.",
+		ChannelAccess::Abandoned if PendingClaim::load(&app.db, kanal.id)?.is_some() => {
+			"Für diesen Kanal läuft bereits eine Übernahme."
+		},
+		ChannelAccess::Abandoned => "Dieser Kanal ist verlassen, du kannst ihn mit `/kanal aneignen` übernehmen.",
+		ChannelAccess::Denied => "Du kannst diesen Kanal erst übernehmen, wenn er verlassen ist.",
+	};
+	embed = embed.field("Für dich", access, false);
+
+	ctx.send(CreateReply::default().embed(embed).ephemeral(true)).await?;
+
+	Ok(())
+}
+
+/// Macht einen Kanal privat, sodass ihn nur noch eingeladene Mitglieder sehen können.
+#[poise::command(slash_command, rename = "privat")]
+async fn set_private(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] mut kanal: GuildChannel,
+	#[description = "Ob der Kanal privat sein soll."] privat: bool,
+) -> Result<(), Error> {
+	let guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	if privacy::is_private(&kanal) == privat {
+		return Err(Error::from(match privat {
+			true => "Dieser Kanal ist bereits privat.",
+			false => "Dieser Kanal ist bereits öffentlich.",
+		}));
+	}
+	ctx.defer_ephemeral().await?;
+
+	match privat {
+		true => privacy::make_private(ctx, &mut kanal, responsible_members(&ctx, ownership.as_ref())).await?,
+		false => {
+			let everyone = PermissionOverwriteType::Role(guild.everyone_role());
+			privacy::set_view_permission(ctx, &mut kanal, everyone, None).await?;
+		},
+	}
+
+	let content = match privat {
+		true => format!(
+			"{} ist nun privat. Mit `/kanal mitglied_hinzufügen` kannst du Leute einladen.",
+			kanal.mention()
+		),
+		false => format!("{} ist nun wieder öffentlich.", kanal.mention()),
+	};
+	ctx.send(CreateReply::default().content(content)).await?;
+	log_both(
+		&ctx,
+		if privat {
+			"Kanal privat gemacht"
+		} else {
+			"Kanal veröffentlicht"
+		},
+		None,
+		Some(&kanal),
+		&[],
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// Lädt ein Mitglied in einen privaten Kanal ein.
+#[poise::command(slash_command, rename = "mitglied_hinzufügen")]
+async fn add_member(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] mut kanal: GuildChannel,
+	#[description = "Das neue Mitglied."] nutzer: User,
+) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	if !privacy::is_private(&kanal) {
+		return Err(Error::from("Dieser Kanal ist öffentlich, jeder kann ihn bereits sehen."));
+	}
+
+	privacy::set_view_permission(ctx, &mut kanal, PermissionOverwriteType::Member(nutzer.id), Some(true)).await?;
+
+	ctx
+		.send(
+			CreateReply::default()
+				.content(format!("{} kann {} nun sehen.", nutzer.mention(), kanal.mention()))
+				.ephemeral(true),
+		)
+		.await?;
+	log_both(&ctx, "Mitglied hinzugefügt", None, Some(&kanal), &[(
+		"Mitglied",
+		format!("{} ({})", nutzer.name, nutzer.id),
+	)])
+	.await?;
+
+	Ok(())
+}
+
+/// Entfernt ein Mitglied aus einem privaten Kanal.
+#[poise::command(slash_command, rename = "mitglied_entfernen")]
+async fn remove_member(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] mut kanal: GuildChannel,
+	#[description = "Das zu entfernende Mitglied."] nutzer: User,
+) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	if !privacy::is_private(&kanal) {
+		return Err(Error::from(
+			"Dieser Kanal ist öffentlich, Mitglieder können nicht entfernt werden.",
+		));
+	}
+	if ownership.is_some_and(|o| o.owner == nutzer.id || o.co_owners.contains(&nutzer.id)) {
+		return Err(Error::from("Besitzer und Mitbesitzer können nicht entfernt werden."));
+	}
+	if nutzer.id == ctx.framework().bot_id {
+		return Err(Error::from("Ich muss den Kanal sehen können, um ihn zu verwalten."));
+	}
+
+	let member = PermissionOverwriteType::Member(nutzer.id);
+	if !kanal.permission_overwrites.iter().any(|o| o.kind == member) {
+		return Err(Error::from(format!(
+			"{} ist kein Mitglied von {}.",
+			nutzer.name,
+			kanal.name()
+		)));
+	}
+	privacy::set_view_permission(ctx, &mut kanal, member, None).await?;
+
+	ctx
+		.send(
+			CreateReply::default()
+				.content(format!("{} kann {} nicht mehr sehen.", nutzer.mention(), kanal.mention()))
+				.ephemeral(true),
+		)
+		.await?;
+	log_both(&ctx, "Mitglied entfernt", None, Some(&kanal), &[(
+		"Mitglied",
+		format!("{} ({})", nutzer.name, nutzer.id),
+	)])
+	.await?;
+
+	Ok(())
+}
+
+/// Beschränkt einen Kanal auf Mitglieder mit einer der selbst wählbaren Rollen.
+#[poise::command(slash_command, rename = "rolle_beschränken")]
+async fn restrict_to_role(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] mut kanal: GuildChannel,
+	#[description = "Die Rolle, die den Kanal sehen darf. Leer lassen, um die Beschränkung aufzuheben."] rolle: Option<Role>,
+) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	let lockable_roles = privacy::lockable_roles(&config);
+	if let Some(rolle) = &rolle {
+		if !lockable_roles.contains(&rolle.id) {
+			return Err(Error::from(format!(
+				"Kanäle können nur auf Rollen beschränkt werden, die man sich selbst geben kann. {} gehört nicht dazu.",
+				rolle.name
+			)));
+		}
+	}
+	ctx.defer_ephemeral().await?;
+
+	// only a single role may see the channel at once
+	for role in lockable_roles {
+		let kind = PermissionOverwriteType::Role(role);
+		if rolle.as_ref().is_some_and(|r| r.id == role) || !kanal.permission_overwrites.iter().any(|o| o.kind == kind) {
+			continue;
+		}
+		privacy::set_view_permission(ctx, &mut kanal, kind, None).await?;
+	}
+
+	let Some(rolle) = rolle else {
+		ctx
+			.send(CreateReply::default().content(format!(
+				"Die Beschränkung von {} auf eine Rolle ist aufgehoben.",
+				kanal.mention()
+			)))
+			.await?;
+		log_both(&ctx, "Rollenbeschränkung aufgehoben", None, Some(&kanal), &[]).await?;
+		return Ok(());
+	};
+
+	// restricted channels are private channels the role is invited to as a whole
+	if !privacy::is_private(&kanal) {
+		privacy::make_private(ctx, &mut kanal, responsible_members(&ctx, ownership.as_ref())).await?;
+	}
+	privacy::set_view_permission(ctx, &mut kanal, PermissionOverwriteType::Role(rolle.id), Some(true)).await?;
+
+	ctx
+		.send(CreateReply::default().content(format!(
+			"{} ist nun auf Mitglieder mit der Rolle {} beschränkt.",
+			kanal.mention(),
+			rolle.name
+		)))
+		.await?;
+	log_both(&ctx, "Kanal auf Rolle beschränkt", None, Some(&kanal), &[(
+		"Rolle",
+		format!("{} ({})", rolle.name, rolle.id),
+	)])
+	.await?;
+
+	Ok(())
+}
+
+/// Setzt den Slowmode eines Kanals.
+#[poise::command(slash_command, rename = "slowmode")]
+async fn set_slowmode(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] kanal: GuildChannel,
+	#[description = "Sekunden zwischen zwei Nachrichten eines Nutzers, 0 zum Deaktivieren."]
+	#[max = 21600]
+	sekunden: u16,
+) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	ctx.defer_ephemeral().await?;
+
+	let after = kanal
+		.id
+		.edit(ctx, EditChannel::default().rate_limit_per_user(sekunden))
+		.await?;
+
+	let content = match sekunden {
+		0 => format!("Der Slowmode in {} ist deaktiviert.", kanal.mention()),
+		_ => format!(
+			"Der Slowmode in {} ist nun auf {} Sekunden gesetzt.",
+			kanal.mention(),
+			sekunden
+		),
+	};
+	ctx.send(CreateReply::default().content(content)).await?;
+	log_detailed(ctx, app, Some(ctx.author()), "Slowmode gesetzt", &after, &[(
+		"Sekunden",
+		sekunden.to_string(),
+	)])
+	.await?;
+
+	Ok(())
+}
+
+/// Heftet eine Nachricht in einem selbstverwalteten Kanal an.
+#[poise::command(slash_command, rename = "nachricht_anheften")]
+async fn pin_message(ctx: Context<'_>, #[description = "Link oder ID der Nachricht."] nachricht: Message) -> Result<(), Error> {
+	set_message_pinned(ctx, nachricht, true).await
+}
+
+/// Löst eine angeheftete Nachricht in einem selbstverwalteten Kanal.
+#[poise::command(slash_command, rename = "nachricht_lösen")]
+async fn unpin_message(ctx: Context<'_>, #[description = "Link oder ID der Nachricht."] nachricht: Message) -> Result<(), Error> {
+	set_message_pinned(ctx, nachricht, false).await
+}
+
+async fn set_message_pinned(ctx: Context<'_>, message: Message, pinned: bool) -> Result<(), Error> {
+	let kanal = message
+		.channel_id
+		.to_channel(ctx)
+		.await?
+		.guild()
+		.ok_or("Diese Nachricht befindet sich nicht auf einem Server.")?;
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	if message.pinned == pinned {
+		return Err(Error::from(match pinned {
+			true => "Diese Nachricht ist bereits angeheftet.",
+			false => "Diese Nachricht ist nicht angeheftet.",
+		}));
+	}
+
+	match pinned {
+		true => message.pin(ctx).await?,
+		false => message.unpin(ctx).await?,
+	}
+
+	let (content, summary) = match pinned {
+		true => ("Ich hab die Nachricht angeheftet.", "Nachricht angeheftet"),
+		false => ("Ich hab die Nachricht gelöst.", "Nachricht gelöst"),
+	};
+	ctx.send(CreateReply::default().content(content).ephemeral(true)).await?;
+	log_detailed(ctx, app, Some(ctx.author()), summary, &kanal, &[
+		("Nachricht", message.link()),
+		("Autor", format!("{} ({})", message.author.name, message.author.id)),
+	])
+	.await?;
+
+	Ok(())
+}
+
+/// Verbietet einem Nutzer für eine Weile, in einem Kanal zu schreiben.
+#[poise::command(slash_command, rename = "stummschalten")]
+async fn mute_user(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] mut kanal: GuildChannel,
+	#[description = "Der stummzuschaltende Nutzer."] nutzer: User,
+	#[description = "Dauer in Minuten."]
+	#[min = 1]
+	#[max = 40320]
+	minuten: u32,
+) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	if nutzer.bot || nutzer.id == ctx.author().id {
+		return Err(Error::from("Diesen Nutzer kannst du nicht stummschalten."));
+	}
+	if ownership.is_some_and(|o| o.owner == nutzer.id || o.co_owners.contains(&nutzer.id)) {
+		return Err(Error::from("Besitzer und Mitbesitzer können nicht stummgeschaltet werden."));
+	}
+	ctx.defer_ephemeral().await?;
+
+	let expires_at = database::now() + i64::from(minuten) * 60;
+	let member = PermissionOverwriteType::Member(nutzer.id);
+	privacy::set_permissions(ctx, &mut kanal, member, MUTED_PERMISSIONS, Some(false)).await?;
+	ChannelMute {
+		channel: kanal.id,
+		user: nutzer.id,
+		muted_by: ctx.author().id,
+		expires_at,
+	}
+	.insert(&app.db)?;
+
+	ctx
+		.send(CreateReply::default().content(format!(
+			"{} ist in {} bis <t:{}:f> stummgeschaltet.",
+			nutzer.mention(),
+			kanal.mention(),
+			expires_at
+		)))
+		.await?;
+	log_detailed(ctx, app, Some(ctx.author()), "Nutzer stummgeschaltet", &kanal, &[
+		("Nutzer", format!("{} ({})", nutzer.name, nutzer.id)),
+		("Bis", format!("<t:{}:f>", expires_at)),
+	])
+	.await?;
+
+	Ok(())
+}
+
+/// Hebt die Stummschaltung eines Nutzers in einem Kanal vorzeitig auf.
+#[poise::command(slash_command, rename = "stummschaltung_aufheben")]
+async fn unmute_user(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] mut kanal: GuildChannel,
+	#[description = "Der stummgeschaltete Nutzer."] nutzer: User,
+) -> Result<(), Error> {
+	let _guild = precheck_and_unwrap(ctx, &kanal)?;
+	let app = ctx.data();
+	let config = app.config();
+
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if !can_edit_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)? {
+		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
+	}
+	if !ChannelMute::delete(&app.db, kanal.id, nutzer.id)? {
+		return Err(Error::from(format!(
+			"{} ist in {} nicht stummgeschaltet.",
+			nutzer.name,
+			kanal.name()
+		)));
+	}
+
+	let member = PermissionOverwriteType::Member(nutzer.id);
+	privacy::set_permissions(ctx, &mut kanal, member, MUTED_PERMISSIONS, None).await?;
+
+	ctx
+		.send(
+			CreateReply::default()
+				.content(format!("{} kann in {} wieder schreiben.", nutzer.mention(), kanal.mention()))
+				.ephemeral(true),
+		)
+		.await?;
+	log_detailed(ctx, app, Some(ctx.author()), "Stummschaltung aufgehoben", &kanal, &[(
+		"Nutzer",
+		format!("{} ({})", nutzer.name, nutzer.id),
+	)])
+	.await?;
+
+	Ok(())
 }
 
-/// Enthält Befehle für den selbstverwalteten Bereich des Servers.
-#[poise::command(
-	slash_command,
-	rename = "kanal",
-	subcommands("create_channel", "update_channel", "delete_channel", "claim_channel")
-)]
-async fn channel_dummy(_ctx: Context<'_>) -> Result<(), Error> {
-	unreachable!() // Upper commands can never be called from discord, all good.
+/// Everyone responsible for a channel, including the bot itself, who must not be locked out of private channels.
+fn responsible_members(ctx: &Context<'_>, ownership: Option<&ChannelOwnership>) -> Vec<UserId> {
+	let mut members = vec![ctx.framework().bot_id, ctx.author().id];
+	if let Some(ownership) = ownership {
+		members.push(ownership.owner);
+		members.extend(&ownership.co_owners);
+	}
+	members.sort();
+	members.dedup();
+	members
 }
 
-/// Erstellt einen neuen Kanal.
-#[poise::command(slash_command, rename = "erstellen")]
-async fn create_channel(
-	ctx: Context<'_>,
-	#[description = "Der Name des Channels."] name: String,
-	#[description = "Wofür ist dieser Channel?"] beschreibung: String,
-) -> Result<(), Error> {
-	let app = ctx.data();
-	let sm = &app.config.self_managment;
+/// Sends the entries as embed fields, split into pages the caller can flip through with buttons.
+async fn send_paginated(ctx: Context<'_>, title: &str, entries: &[(String, String)], page_size: usize) -> Result<(), Error> {
+	let pages = entries.chunks(page_size).collect::<Vec<_>>();
+	if pages.is_empty() {
+		return Err(Error::from("Es gibt keine Einträge."));
+	}
 
-	let guild_id = ctx
-		.guild_id()
-		.ok_or("Dieser Befehl kann nur auf einem Server ausgeführt werden.")?;
+	let prev_id = format!("{}page_prev", ctx.id());
+	let next_id = format!("{}page_next", ctx.id());
+	let page_embed = |page: usize| {
+		let embed = CreateEmbed::new()
+			.title(title)
+			.fields(pages[page].iter().map(|(name, value)| (name.as_str(), value.as_str(), false)));
+		match pages.len() {
+			1 => embed,
+			_ => embed.footer(CreateEmbedFooter::new(format!("Seite {} von {}", page + 1, pages.len()))),
+		}
+	};
+	let page_buttons = |page: usize| {
+		if pages.len() == 1 {
+			return vec![];
+		}
+		vec![CreateActionRow::Buttons(vec![
+			CreateButton::new(&prev_id).label("Zurück").disabled(page == 0),
+			CreateButton::new(&next_id).label("Weiter").disabled(page + 1 == pages.len()),
+		])]
+	};
 
-	// check how long user has been on the server
-	let joined_at = ctx
-		.author_member()
-		.await
-		.and_then(|m| m.joined_at)
-		.ok_or("Ich konnte dein Mitgliedsalter leider nicht abfragen.")?;
-	let joined_at = joined_at.timestamp();
-	let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs() as i64;
+	let mut page = 0;
+	let reply = ctx
+		.send(
+			CreateReply::default()
+				.embed(page_embed(page))
+				.components(page_buttons(page))
+				.ephemeral(true),
+		)
+		.await?;
+	if pages.len() == 1 {
+		return Ok(());
+	}
 
-	if now - joined_at < sm.join_age_limit {
-		return Err(Error::from(
-			"Du bist noch nicht lange genug auf dem Server, um einen Kanal zu erstellen.",
-		));
+	loop {
+		let interaction = {
+			let prev_id = prev_id.clone();
+			let next_id = next_id.clone();
+			ComponentInteractionCollector::new(ctx)
+				.author_id(ctx.author().id)
+				.timeout(LIST_TIMEOUT)
+				.filter(move |i| i.data.custom_id == prev_id || i.data.custom_id == next_id)
+				.await
+		};
+		let Some(interaction) = interaction else {
+			reply.edit(ctx, CreateReply::default().components(vec![])).await?;
+			return Ok(());
+		};
+
+		page = match interaction.data.custom_id == prev_id {
+			true => page.saturating_sub(1),
+			false => (page + 1).min(pages.len() - 1),
+		};
+		interaction
+			.create_response(
+				ctx,
+				CreateInteractionResponse::UpdateMessage(
+					CreateInteractionResponseMessage::new()
+						.embed(page_embed(page))
+						.components(page_buttons(page)),
+				),
+			)
+			.await?;
 	}
+}
 
-	// get current channels owned by user
-	let owned_channels = get_user_channel(&ctx).await?;
-	trace!(
-		"User owns {} channels: {:?}",
-		owned_channels.len(),
-		owned_channels.iter().map(|c| c.name.clone()).collect::<Vec<_>>()
-	);
+/// Embed field describing a channel in the channel list.
+fn list_entry(
+	db: &Database,
+	channel: &GuildChannel,
+	ownership: Option<&ChannelOwnership>,
+	config: &SelfManagement,
+) -> Result<(String, String), Error> {
+	let mut topic = topic_or_placeholder(channel).to_string();
+	if topic.chars().count() > LIST_TOPIC_LENGTH {
+		topic = topic.chars().take(LIST_TOPIC_LENGTH - 1).collect::<String>() + "…";
+	}
 
-	// check if user is allowed to create more channels
-	if owned_channels.len() >= sm.limit as usize {
-		return Err(Error::from(format!("Du darfst nur maximal {} Kanäle besitzen.", sm.limit)));
+	let owner = match ownership {
+		Some(ownership) => ownership.owner.mention().to_string(),
+		None => "-".to_string(),
+	};
+	let mut status = format!(
+		"Besitzer: {} · Letzte Aktivität: <t:{}:R>",
+		owner,
+		last_activity(db, channel)?
+	);
+	if is_abandoned(db, channel, config)? {
+		status += " · verlassen";
+	}
+	if privacy::is_private(channel) {
+		status += " · privat";
 	}
 
-	// create channel in category (will fail if in different guild)
-	ctx.defer_ephemeral().await?;
-	let channel = guild_id
-		.create_channel(
-			ctx,
-			CreateChannel::new(name)
-				.topic(inject_ownership(&beschreibung, ctx.author(), app))
-				.category(sm.category),
-		)
-		.await?;
+	Ok((channel.name.clone(), format!("{}\n{}\n{}", channel.mention(), topic, status)))
+}
+
+/// Moves the channel into the archive category and prevents anyone from writing in it.
+async fn archive_channel(
+	http: impl CacheHttp,
+	db: &Database,
+	channel: &GuildChannel,
+	archive: &Archive,
+	archived_by: UserId,
+) -> Result<(), Error> {
+	// strip sending permissions from every overwrite, otherwise members with explicit permissions could still write
+	let mut overwrites = channel.permission_overwrites.clone();
+	let everyone = PermissionOverwriteType::Role(channel.guild_id.everyone_role());
+	if !overwrites.iter().any(|o| o.kind == everyone) {
+		overwrites.push(PermissionOverwrite {
+			allow: Permissions::empty(),
+			deny: Permissions::empty(),
+			kind: everyone,
+		});
+	}
+	for overwrite in &mut overwrites {
+		overwrite.allow.remove(ARCHIVE_LOCKED_PERMISSIONS);
+		overwrite.deny.insert(ARCHIVE_LOCKED_PERMISSIONS);
+	}
 
-	// recent undocumented change discards any newlines in the topic during creation, so we have to set it again via edit
-	// call (costs another api call, great)
 	channel
 		.id
 		.edit(
-			ctx,
-			EditChannel::default().topic(inject_ownership(&beschreibung, ctx.author(), app)),
+			&http,
+			EditChannel::default()
+				.category(ChannelId::new(archive.category))
+				.permissions(overwrites),
 		)
 		.await?;
 
-	// inform user about success
-	ctx
-		.send(CreateReply::default().content(format!("Ich hab deinen Kanal erstellt: {}", channel.mention())))
-		.await?;
-
-	log_both(&ctx, "Kanal erstellt", None, Some(&channel)).await?;
-
-	sort(&ctx, &guild_id).await?;
+	ArchivedChannel {
+		channel: channel.id,
+		category: channel
+			.parent_id
+			.ok_or("Dieser Kanal befindet sich nicht unterhalb einer Kategorie.")?,
+		permission_overwrites: channel.permission_overwrites.clone(),
+		archived_by,
+		archived_at: database::now(),
+	}
+	.insert(db)?;
 
 	Ok(())
 }
 
-/// Modifiziert den angegebenen Kanal.
-#[poise::command(slash_command, rename = "ändern")]
-async fn update_channel(
-	ctx: Context<'_>,
-	#[description = "Der Name des Channels."] kanal: GuildChannel,
-	#[description = "Eine neuer Name."] name: Option<String>,
-	#[description = "Eine neue Beschreibung."] beschreibung: Option<String>,
-	#[description = "Setzt den NSFW Status."] nsfw: Option<bool>,
-) -> Result<(), Error> {
-	let guild = precheck_and_unwrap(ctx, &kanal)?;
-	let app = ctx.data();
+/// Deletes archived channels once their retention period is over.
+async fn purge_archives(ctx: &poise::serenity_prelude::Context, app: &AppState) -> Result<(), Error> {
+	let config = app.config();
+	let Some(archive) = &config.self_managment.archive else {
+		return Ok(());
+	};
 
-	// enforce ownership, if enabled
-	if !can_edit_channel(&ctx.author().id, &kanal, &app.config.self_managment) {
-		return Err(Error::from("Du darfst diesen Kanal nicht bearbeiten."));
-	}
-	ctx.defer_ephemeral().await?;
+	// a single channel failing to be deleted must not hold up the others, it is simply retried next time
+	let expired = ArchivedChannel::archived_before(&app.db, database::now() - archive.retention as i64)?;
+	for channel_id in expired {
+		info!("Deleting archived channel {} after retention period", channel_id);
+
+		let channel = match channel_id.delete(ctx).await {
+			Ok(channel) => channel.guild(),
+			// channel might have been deleted by someone else in the meantime
+			Err(err) if is_not_found(&err) => {
+				warn!("Archived channel {} no longer exists", channel_id);
+				None
+			},
+			Err(err) => {
+				warn!("Failed to delete archived channel {}: {}", channel_id, err);
+				continue;
+			},
+		};
 
-	let after = {
-		let mut edit_channel = EditChannel::default()
-			.name(name.as_ref().unwrap_or(&kanal.name))
-			.nsfw(nsfw.unwrap_or(kanal.nsfw));
-		let topic = beschreibung.as_ref();
-		if let Some(topic) = topic {
-			edit_channel = edit_channel.topic(inject_ownership(topic, ctx.author(), app));
+		ArchivedChannel::delete(&app.db, channel_id)?;
+		ChannelOwnership::delete(&app.db, channel_id)?;
+
+		if let Some(channel) = channel {
+			if let Err(err) = log_both_as(ctx, app, None, "Archivierter Kanal gelöscht", Some(&channel), None, &[]).await {
+				warn!("Failed to log deletion of archived channel {}: {}", channel_id, err);
+			}
 		}
+	}
 
-		kanal.id.edit(ctx, edit_channel)
-	};
+	Ok(())
+}
 
-	// channel edits have absolute bonkers rate limits, so to prevent a lot of work to stack up, we use aggressive timeouts
-	let after = tokio::time::timeout(CHANNEL_EDIT_TIMEOUT, after).await??;
+/// Starts all periodic tasks of the self-managed category.
+pub fn spawn_background_tasks(ctx: &poise::serenity_prelude::Context, app: &AppState) {
+	sorting::spawn_sort_task(ctx, app);
+
+	let ctx = ctx.clone();
+	let app = app.clone();
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(BACKGROUND_TASK_INTERVAL);
+		loop {
+			interval.tick().await;
+			if let Err(err) = sweeper::sweep_inactive_channels(&ctx, &app).await {
+				error!("Failed to sweep inactive channels: {}", err);
+			}
+			if let Err(err) = purge_archives(&ctx, &app).await {
+				error!("Failed to purge archived channels: {}", err);
+			}
+			if let Err(err) = claim::finalize_claims(&ctx, &app).await {
+				error!("Failed to finalize channel claims: {}", err);
+			}
+			if let Err(err) = mutes::lift_expired_mutes(&ctx, &app).await {
+				error!("Failed to lift expired channel mutes: {}", err);
+			}
+			if let Err(err) = voice::remove_empty_rooms(&ctx, &app).await {
+				error!("Failed to remove empty voice rooms: {}", err);
+			}
+			// keeps activity order up to date and picks up changes of the configured strategy
+			sorting::request_sort(&app);
+		}
+	});
+}
 
-	// inform user about success
+/// Checks if the author is a server moderator, who may bypass ownership for some actions.
+async fn is_channel_admin(ctx: &Context<'_>) -> bool {
 	ctx
-		.send(CreateReply::default().content(format!("Ich hab den Channel modifiziert: {}", kanal.name())))
-		.await?;
-	log_both(&ctx, "Channel aktualisiert", Some(&kanal), Some(&after)).await?;
-
-	// sort channel list to maintain peace and harmony
-	sort(&ctx, &guild).await?;
-
-	Ok(())
+		.author_member()
+		.await
+		.and_then(|member| member.permissions)
+		.is_some_and(|permissions| permissions.manage_channels())
 }
 
-/// Beansprucht den angegebenen Kanal.
-#[poise::command(slash_command, rename = "aneignen")]
-async fn claim_channel(ctx: Context<'_>, #[description = "Der Name des Channels."] kanal: GuildChannel) -> Result<(), Error> {
+/// Fügt einen Mitbesitzer zum angegebenen Kanal hinzu.
+#[poise::command(slash_command, rename = "mitbesitzer_hinzufügen")]
+async fn add_co_owner(
+	ctx: Context<'_>,
+	#[description = "Der Name des Channels."] kanal: GuildChannel,
+	#[description = "Der neue Mitbesitzer."] nutzer: User,
+) -> Result<(), Error> {
 	let _guild = precheck_and_unwrap(ctx, &kanal)?;
 	let app = ctx.data();
+	let config = app.config();
 
-	if !app.config.self_managment.claiming || !app.config.self_managment.ownership {
-		return Err(Error::from("Kanalübernahmen sind deaktiviert."));
+	if !config.self_managment.ownership {
+		return Err(Error::from("Kanalbesitz ist deaktiviert."));
 	}
 
-	// enforce ownership, if enabled
-	if !can_edit_channel(&ctx.author().id, &kanal, &app.config.self_managment) {
-		return Err(Error::from("Du darfst diesen Kanal nicht übernehmen."));
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?
+		.ok_or("Dieser Kanal hat keinen Besitzer, dem du Mitbesitzer zur Seite stellen könntest.")?;
+	if !can_manage_channel(&app.db, &ctx.author().id, &kanal, Some(&ownership), &config.self_managment)? {
+		return Err(Error::from("Nur der Besitzer darf Mitbesitzer hinzufügen."));
+	}
+	if nutzer.bot {
+		return Err(Error::from("Bots können keine Mitbesitzer werden."));
+	}
+	if nutzer.id == ownership.owner {
+		return Err(Error::from("Der Besitzer kann nicht gleichzeitig Mitbesitzer sein."));
 	}
-	ctx.defer_ephemeral().await?;
-
-	// check if channel contains owner information
-	let meta = ChannelMeta::from_channel(&kanal);
-	let topic = match meta {
-		Some(_) => remove_meta(kanal.topic.as_ref().unwrap()),
-		None => kanal.topic.as_deref().unwrap_or("").to_string(),
-	};
-
-	let after = kanal
-		.id
-		.edit(ctx, EditChannel::default().topic(inject_ownership(&topic, ctx.author(), app)));
 
-	// channel edits have absolute bonkers rate limits, so to prevent a lot of work to stack up, we use aggressives timeouts
-	tokio::time::timeout(CHANNEL_EDIT_TIMEOUT, after).await??;
+	if !ChannelOwnership::add_co_owner(&app.db, kanal.id, nutzer.id)? {
+		return Err(Error::from(format!(
+			"{} ist bereits Mitbesitzer von {}.",
+			nutzer.name,
+			kanal.name()
+		)));
+	}
+	privacy::grant_access(ctx, &kanal, nutzer.id).await?;
 
-	// inform user about success
 	ctx
-		.send(CreateReply::default().content(format!("Du bist nun der neue Besitzer von: {}", kanal.name())))
+		.send(
+			CreateReply::default()
+				.content(format!("{} ist nun Mitbesitzer von {}.", nutzer.mention(), kanal.mention()))
+				.ephemeral(true),
+		)
 		.await?;
+	log_both(&ctx, "Mitbesitzer hinzugefügt", None, Some(&kanal), &[(
+		"Mitbesitzer",
+		nutzer.mention().to_string(),
+	)])
+	.await?;
 
 	Ok(())
 }
 
-/// Löscht den angegebenen Kanal.
-#[poise::command(slash_command, rename = "löschen")]
-async fn delete_channel(
+/// Entfernt einen Mitbesitzer vom angegebenen Kanal.
+#[poise::command(slash_command, rename = "mitbesitzer_entfernen")]
+async fn remove_co_owner(
 	ctx: Context<'_>,
-	#[description = "Der Channel, den du löschen möchtest."] kanal: GuildChannel,
+	#[description = "Der Name des Channels."] kanal: GuildChannel,
+	#[description = "Der Mitbesitzer, der entfernt werden soll."] nutzer: User,
 ) -> Result<(), Error> {
 	let _guild = precheck_and_unwrap(ctx, &kanal)?;
 	let app = ctx.data();
-
-	// enforce ownership, if enabled
-	if !can_edit_channel(&ctx.author().id, &kanal, &app.config.self_managment) {
-		return Err(Error::from("Du darfst diesen Kanal nicht löschen."));
+	let config = app.config();
+
+	// co-owners are always allowed to step down by themselves
+	let ownership = ChannelOwnership::load(&app.db, kanal.id)?;
+	if nutzer.id != ctx.author().id
+		&& !can_manage_channel(&app.db, &ctx.author().id, &kanal, ownership.as_ref(), &config.self_managment)?
+	{
+		return Err(Error::from("Nur der Besitzer darf Mitbesitzer entfernen."));
 	}
 
-	// perform deletion
-	ctx.defer_ephemeral().await?;
-	kanal.delete(ctx).await?;
-
-	// inform user about success (only possible if command was not issued from the very same channel)
-	if ctx.channel_id() != kanal.id {
-		ctx
-			.send(CreateReply::default().content(format!("Ich hab den Kanal gelöscht: {}", kanal.name())))
-			.await?;
+	if !ChannelOwnership::remove_co_owner(&app.db, kanal.id, nutzer.id)? {
+		return Err(Error::from(format!(
+			"{} ist kein Mitbesitzer von {}.",
+			nutzer.name,
+			kanal.name()
+		)));
 	}
 
-	log_both(&ctx, "Kanal gelöscht", Some(&kanal), None).await?;
+	ctx
+		.send(
+			CreateReply::default()
+				.content(format!(
+					"{} ist nun kein Mitbesitzer von {} mehr.",
+					nutzer.mention(),
+					kanal.mention()
+				))
+				.ephemeral(true),
+		)
+		.await?;
+	log_both(&ctx, "Mitbesitzer entfernt", None, Some(&kanal), &[(
+		"Mitbesitzer",
+		nutzer.mention().to_string(),
+	)])
+	.await?;
 
 	Ok(())
 }
 
+/// Logs in both logging channels. Details are only included in the detailed log, since they usually contain users.
 async fn log_both(
 	ctx: &Context<'_>,
 	summary: &str,
 	before: Option<&GuildChannel>,
 	after: Option<&GuildChannel>,
+	details: &[(&str, String)],
+) -> Result<(), Error> {
+	log_both_as(ctx, ctx.data(), Some(ctx.author()), summary, before, after, details).await
+}
+
+/// Same as `log_both`, but also usable outside of commands. Actions without user were performed by the bot itself.
+async fn log_both_as(
+	http: impl CacheHttp,
+	app: &AppState,
+	user: Option<&User>,
+	summary: &str,
+	before: Option<&GuildChannel>,
+	after: Option<&GuildChannel>,
+	details: &[(&str, String)],
 ) -> Result<(), Error> {
-	let cfg = &ctx.data().config.self_managment;
-	let user = ctx.author();
+	let config = app.config();
+	let cfg = &config.self_managment;
+
+	// audit trail is written first, so it is complete even if logging to Discord fails
+	let channel = after.or(before).ok_or("logged action has neither before nor after state")?;
+	AuditEvent::new(channel.id, user.map(|user| user.id), summary, before, after, details).insert(&app.db)?;
 
 	// logging for everyone without user
-	log_modification(ctx, &cfg.logging.map(|id| ChannelId::new(id)), summary, before, after, None).await?;
+	log_modification(
+		&http,
+		&cfg.logging.map(|id| ChannelId::new(id)),
+		summary,
+		before,
+		after,
+		None,
+		&[],
+	)
+	.await?;
 
 	// internal logging with executing user
 	log_modification(
-		ctx,
+		&http,
 		&cfg.logging_detailed.map(|id| ChannelId::new(id)),
 		summary,
 		before,
 		after,
-		Some(user),
+		user,
+		details,
 	)
 	.await?;
 
 	Ok(())
 }
 
+/// Logs actions only server moderators need to know about, like owners moderating their channel.
+async fn log_detailed(
+	http: impl CacheHttp,
+	app: &AppState,
+	user: Option<&User>,
+	summary: &str,
+	channel: &GuildChannel,
+	details: &[(&str, String)],
+) -> Result<(), Error> {
+	AuditEvent::new(channel.id, user.map(|user| user.id), summary, None, Some(channel), details).insert(&app.db)?;
+
+	log_modification(
+		http,
+		&app.config().self_managment.logging_detailed.map(ChannelId::new),
+		summary,
+		None,
+		Some(channel),
+		user,
+		details,
+	)
+	.await
+}
+
 /// Logs modifications in the configured logging channel for transparency and general awareness.
 async fn log_modification(
-	ctx: &Context<'_>,
+	http: impl CacheHttp,
 	channel_id: &Option<ChannelId>,
 	summary: &str,
 	before: Option<&GuildChannel>,
 	after: Option<&GuildChannel>,
 	user: Option<&User>,
+	details: &[(&str, String)],
 ) -> Result<(), Error> {
 	// check if logging is enabled
 	let channel_id: ChannelId = match channel_id {
@@ -304,7 +1869,7 @@ async fn log_modification(
 			Some(after) => {
 				e = e
 					.field("Name", &after.name, true)
-					.field("Beschreibung", after.topic.as_ref().unwrap(), true);
+					.field("Beschreibung", topic_or_placeholder(after), true);
 				field_set = true;
 			},
 		},
@@ -313,35 +1878,15 @@ async fn log_modification(
 			None => {
 				e = e
 					.field("Name", &before.name, true)
-					.field("Beschreibung", before.topic.as_ref().unwrap(), true);
+					.field("Beschreibung", topic_or_placeholder(before), true);
 				field_set = true;
 			},
 
 			// channel was modified
 			Some(after) => {
-				// check if name changed
-				if before.name != after.name {
-					e = e.field("Name", format!("- `{}`\n\n+ `{}`", before.name, after.name), true);
-					field_set = true;
-				}
-
-				// check if description changed
-				if before.topic != after.topic {
-					e = e.field(
-						"Beschreibung",
-						format!(
-							"- `{}`\n\n+ `{}`",
-							before.topic.as_deref().unwrap_or(""),
-							after.topic.as_deref().unwrap_or("")
-						),
-						true,
-					);
-					field_set = true;
-				}
-
-				// check if nsfw flag changed
-				if before.topic != after.topic {
-					e = e.field("NSFW", if after.nsfw { "ja" } else { "nein" }, true);
+				let changes = ChannelSnapshot::from(before).changes(&ChannelSnapshot::from(after));
+				for (name, before, after) in changes {
+					e = e.field(name, format!("- `{}`\n\n+ `{}`", before, after), true);
 					field_set = true;
 				}
 			},
@@ -351,66 +1896,78 @@ async fn log_modification(
 	if let Some(user) = user {
 		e = e.field("Nutzer", format!("{} ({})", user.name, user.id), false);
 	}
+	for (name, value) in details {
+		e = e.field(*name, value, false);
+	}
 
 	if field_set {
-		channel_id.send_message(ctx, CreateMessage::default().embed(e)).await?;
+		channel_id.send_message(http, CreateMessage::default().embed(e)).await?;
 	}
 
 	Ok(())
 }
 
-async fn get_user_channel(ctx: &Context<'_>) -> Result<Vec<GuildChannel>, Error> {
-	let guild = ctx
-		.guild()
-		.ok_or("Dieser Befehl kann nur in einem Server ausgeführt werden.")?
-		.clone();
-	let user = ctx.author();
-	let sm = &ctx.data().config.self_managment;
-	let channels = guild.channels(ctx).await?;
+/// Validates the name of a new or renamed channel. Rejections are logged in the detailed log, so moderators can keep an
+/// eye on people trying to sneak in inappropriate names.
+async fn check_channel_name(ctx: &Context<'_>, guild: GuildId, name: &str, channel: Option<ChannelId>) -> Result<(), Error> {
+	check_channel_name_as(ctx, ctx.data(), ctx.author(), guild, name, channel).await
+}
 
-	// keep only channels that are in the category and have ownership meta
-	let channels = channels
-		.into_values()
-		.filter(|c| match c.parent_id {
-			Some(id) => id == sm.category,
-			None => false,
-		})
-		.collect::<Vec<_>>();
-	trace!("found {} channels in self_management category", channels.len());
+/// Same as `check_channel_name`, but also usable outside of commands.
+async fn check_channel_name_as(
+	http: impl CacheHttp,
+	app: &AppState,
+	user: &User,
+	guild: GuildId,
+	name: &str,
+	channel: Option<ChannelId>,
+) -> Result<(), Error> {
+	let config = app.config();
+	let sm = &config.self_managment;
 
-	// filter channels by ownership
-	let channels = channels
-		.into_iter()
-		.filter_map(|c| match ChannelMeta::from_channel(&c) {
-			Some(meta) => {
-				if meta.owner == user.id {
-					Some(c)
-				} else {
-					None
-				}
-			},
-			None => None,
-		})
-		.collect::<Vec<_>>();
+	let channels = guild.channels(http.http()).await?.into_values().collect::<Vec<_>>();
+	let Err(rejection) = names::validate(name, sm.names.as_ref(), &channels, |c| is_self_managed(c, sm), channel) else {
+		return Ok(());
+	};
 
-	Ok(channels)
+	info!(
+		"Rejected channel name '{}' of {} ({}): {}",
+		name, user.name, user.id, rejection.detail
+	);
+	if let Some(logging_detailed) = sm.logging_detailed {
+		let e = CreateEmbed::default()
+			.title("Kanalname abgelehnt")
+			.field("Name", name, true)
+			.field("Grund", &rejection.detail, true)
+			.field("Nutzer", format!("{} ({})", user.name, user.id), false);
+		ChannelId::new(logging_detailed)
+			.send_message(http, CreateMessage::default().embed(e))
+			.await?;
+	}
+
+	Err(Error::from(rejection.message))
 }
 
-fn inject_ownership(topic: &str, user: &User, app: &AppState) -> String {
-	if !app.config.self_managment.ownership {
-		return topic.to_string();
-	}
+/// Returns the self-managed channels owned by the user.
+async fn owned_channels(http: impl CacheHttp, app: &AppState, guild: GuildId, user: UserId) -> Result<Vec<GuildChannel>, Error> {
+	let config = app.config();
+	let sm = &config.self_managment;
+	let channels = guild.channels(http.http()).await?;
 
-	let meta = ChannelMeta {
-		owner: user.id,
-	};
-	let json = serde_json::to_string(&meta).expect("failed to serialize ownership meta");
-	format!("{}\n\n{}", topic, json)
+	// keep only channels that are in the category
+	let channels = channels.into_values().filter(|c| is_self_managed(c, sm)).collect::<Vec<_>>();
+	trace!("found {} channels in self_management category", channels.len());
+
+	// filter channels by ownership
+	let owned = ChannelOwnership::owned_by(&app.db, user)?;
+	let channels = channels.into_iter().filter(|c| owned.contains(&c.id)).collect::<Vec<_>>();
+
+	Ok(channels)
 }
 
 /// Perform some sanity checks and unwrap the guild object
 fn precheck_and_unwrap(ctx: Context<'_>, channel: &GuildChannel) -> Result<GuildId, Error> {
-	let app = ctx.data();
+	let config = ctx.data().config();
 
 	// check if channel belongs to same guild (prevents deletion from outside guilds)
 	let guild = ctx
@@ -421,10 +1978,10 @@ fn precheck_and_unwrap(ctx: Context<'_>, channel: &GuildChannel) -> Result<Guild
 	}
 
 	// check if channel actually belong into self-managed category
-	let channel_category_id = channel
-		.parent_id
-		.ok_or("Dieser Kanal befindet sich nicht unterhalb einer Kategorie.")?;
-	if app.config.self_managment.category != u64::from(channel_category_id) {
+	if channel.parent_id.is_none() {
+		return Err(Error::from("Dieser Kanal befindet sich nicht unterhalb einer Kategorie."));
+	}
+	if !is_self_managed(channel, &config.self_managment) {
 		return Err(Error::from(
 			"Dieser Channel befindet sich nicht in der richtigen Kategorie und kann nicht gelöscht werden.",
 		));
@@ -433,80 +1990,247 @@ fn precheck_and_unwrap(ctx: Context<'_>, channel: &GuildChannel) -> Result<Guild
 	Ok(guild)
 }
 
+/// Relationship between a user and a self-managed channel.
+#[derive(Debug, PartialEq, Eq)]
+enum ChannelAccess {
+	/// Ownership is not enforced, since it is disabled or unknown.
+	Free,
+	Owner,
+	CoOwner,
+	/// Channel has been abandoned by its owner, so everyone may edit and claim it, but not delete or transfer it.
+	Abandoned,
+	Denied,
+}
+
 /// Checks if the user is allowed to edit the channel.
-fn can_edit_channel(user: &UserId, channel: &GuildChannel, config: &SelfManagement) -> bool {
+fn can_edit_channel(
+	db: &Database,
+	user: &UserId,
+	channel: &GuildChannel,
+	ownership: Option<&ChannelOwnership>,
+	config: &SelfManagement,
+) -> Result<bool, Error> {
+	Ok(channel_access(db, user, channel, ownership, config)? != ChannelAccess::Denied)
+}
+
+/// Checks if the user is allowed to delete the channel or hand it to someone else, which co-owners are not. Abandoned
+/// channels have to be claimed first, only moderators may delete or transfer them right away.
+fn can_manage_channel(
+	db: &Database,
+	user: &UserId,
+	channel: &GuildChannel,
+	ownership: Option<&ChannelOwnership>,
+	config: &SelfManagement,
+) -> Result<bool, Error> {
+	Ok(matches!(
+		channel_access(db, user, channel, ownership, config)?,
+		ChannelAccess::Free | ChannelAccess::Owner
+	))
+}
+
+fn channel_access(
+	db: &Database,
+	user: &UserId,
+	channel: &GuildChannel,
+	ownership: Option<&ChannelOwnership>,
+	config: &SelfManagement,
+) -> Result<ChannelAccess, Error> {
 	// always allow edit if ownership is disabled
 	if !config.ownership {
-		return true;
+		return Ok(ChannelAccess::Free);
 	}
 
-	// check abadonment state
-	let is_abandoned = channel
-		.last_message_id
-		.map(|id| {
-			let created = id.created_at();
-			let now = poise::serenity_prelude::Timestamp::now();
-			let diff = now.timestamp() - created.timestamp();
-			diff as u64 > config.abandon_after
-		})
-		.unwrap_or(false);
+	// allow if user is owner or there is no ownership information
+	let Some(ownership) = ownership else {
+		// if there is no ownership information, channel is always considered free for all
+		return Ok(ChannelAccess::Free);
+	};
+	if ownership.owner == *user {
+		return Ok(ChannelAccess::Owner);
+	}
+	if ownership.co_owners.contains(user) {
+		return Ok(ChannelAccess::CoOwner);
+	}
+
+	// otherwise channel needs to be abandoned to be editable
+	if is_abandoned(db, channel, config)? {
+		return Ok(ChannelAccess::Abandoned);
+	}
+
+	Ok(ChannelAccess::Denied)
+}
 
-	// allow if user is owner or there is no meta
-	let meta = ChannelMeta::from_channel(channel);
-	if let Some(meta) = meta {
-		if meta.owner == *user {
-			return true;
+/// Channels are abandoned if nobody wrote in them for a while, channels without any message never are.
+fn is_abandoned(db: &Database, channel: &GuildChannel, config: &SelfManagement) -> Result<bool, Error> {
+	Ok(
+		last_message_id(db, channel)?
+			.map(|id| {
+				let created = id.created_at();
+				let now = poise::serenity_prelude::Timestamp::now();
+				let diff = now.timestamp() - created.timestamp();
+				diff as u64 > config.abandon_after
+			})
+			.unwrap_or(false),
+	)
+}
+
+/// Unix timestamp of the last message in the channel, or of its creation if nobody wrote anything yet.
+fn last_activity(db: &Database, channel: &GuildChannel) -> Result<i64, Error> {
+	Ok(
+		last_message_id(db, channel)?
+			.map(|id| id.created_at().timestamp())
+			.unwrap_or_else(|| channel.id.created_at().timestamp()),
+	)
+}
+
+/// Last message in the channel, skipping the inactivity warning and claim notification the bot posted itself, since
+/// those would otherwise keep the channel active.
+fn last_message_id(db: &Database, channel: &GuildChannel) -> Result<Option<MessageId>, Error> {
+	let mut warning = InactivityWarning::load(db, channel.id)?;
+	let mut claim = PendingClaim::load(db, channel.id)?;
+
+	let mut last = channel.last_message_id;
+	while let Some(id) = last {
+		if let Some(warning) = warning.take_if(|warning| warning.message == id) {
+			last = warning.previous_message;
+		} else if let Some(claim) = claim.take_if(|claim| claim.message == id) {
+			last = claim.previous_message;
+		} else {
+			break;
 		}
-	} else {
-		// if there is no meta, channel is always considered free for all
-		return true;
 	}
+	Ok(last)
+}
 
-	// otherwise channel needs to be abandoned to be editable
-	if is_abandoned {
-		return true;
+/// Imports ownership information from the JSON line that older versions appended to the topic of every channel and
+/// removes that line afterwards. Channels without such a line are left untouched, so running this again is harmless.
+pub async fn import_legacy_ownership(ctx: &poise::serenity_prelude::Context, app: &AppState) -> Result<(), Error> {
+	let channels = self_managed_channels(ctx, &app.config().self_managment).await?;
+	for channel in &channels {
+		let Some(meta) = ChannelMeta::from_channel(channel) else {
+			continue;
+		};
+		info!("Importing legacy ownership of channel {} ({})", channel.name, channel.id);
+
+		ChannelOwnership {
+			channel: channel.id,
+			owner: meta.owner,
+			creator: meta.owner,
+			created_at: channel.id.created_at().timestamp(),
+			co_owners: Vec::new(),
+		}
+		.insert(&app.db)?;
+
+		let topic = remove_meta(channel.topic.as_deref().unwrap_or(""));
+		channel.id.edit(ctx, EditChannel::default().topic(topic)).await?;
 	}
 
-	false
+	Ok(())
 }
 
-fn remove_meta(str: &str) -> String {
-	let mut lines = str.trim().lines().collect::<Vec<_>>();
-	lines.truncate(lines.len() - 1);
-	lines.join("\n").trim().to_string()
+/// Fetches all channels in the self-managed categories, without requiring a command context.
+async fn self_managed_channels(http: impl CacheHttp, config: &SelfManagement) -> Result<Vec<GuildChannel>, Error> {
+	let category_id = ChannelId::new(*config.category.first().ok_or("no self-managed category configured")?);
+	let category = category_id
+		.to_channel(&http)
+		.await?
+		.guild()
+		.ok_or("self-managed category is not a guild channel")?;
+
+	let channels = category.guild_id.channels(http.http()).await?;
+	Ok(channels.into_values().filter(|c| is_self_managed(c, config)).collect())
 }
 
-async fn sort(ctx: &Context<'_>, guild: &GuildId) -> Result<(), Error> {
-	let app = ctx.data();
-	let category_id = ChannelId::new(app.config.self_managment.category);
+/// Checks if the channel belongs to the self-managed area, which might span multiple categories.
+fn is_self_managed(channel: &GuildChannel, config: &SelfManagement) -> bool {
+	channel.parent_id.is_some_and(|id| config.category.contains(&id.get()))
+}
 
-	let channels = guild.channels(ctx).await?;
-	let mut category_channels = channels
+/// Picks the first self-managed category with room for another channel.
+fn category_with_room(channels: &HashMap<ChannelId, GuildChannel>, config: &SelfManagement) -> Result<ChannelId, Error> {
+	config
+		.category
 		.iter()
-		// remove all channels without parent
-		.filter_map(|(_, channel)| channel.parent_id.map(|parent_id| (parent_id, channel)))
-		// remove channels from different categories
-		.filter(|(parent_id, _)| category_id == *parent_id)
-		// drop parent information
-		.map(|(_, channel)| channel)
-		.collect::<Vec<_>>();
+		.map(|id| ChannelId::new(*id))
+		.find(|category| {
+			let count = channels.values().filter(|c| c.parent_id == Some(*category)).count();
+			count < CATEGORY_CHANNEL_LIMIT
+		})
+		.ok_or(Error::from(
+			"Der selbstverwaltete Bereich ist voll, es können keine weiteren Kanäle erstellt werden.",
+		))
+}
 
-	category_channels.sort_by(|x, y| x.name.cmp(&y.name));
+/// Forgets about channels that were deleted, no matter by whom.
+pub fn handle_channel_delete(app: &AppState, channel: &GuildChannel) -> Result<(), Error> {
+	ChannelOwnership::delete(&app.db, channel.id)?;
+	ArchivedChannel::delete(&app.db, channel.id)?;
+	InactivityWarning::delete(&app.db, channel.id)?;
+	PendingClaim::delete(&app.db, channel.id)?;
+	PinnedChannel::delete(&app.db, channel.id)?;
+	ChannelMute::delete_channel(&app.db, channel.id)?;
+	TemporaryRoom::delete(&app.db, channel.id)?;
+	Ok(())
+}
 
-	guild
-		.reorder_channels(
-			ctx,
-			category_channels
-				.into_iter()
-				.enumerate()
-				.map(|(idx, channel)| (channel.id, idx as u64)),
-		)
-		.await?;
+/// Handles the owner objecting to a claim of their channel.
+pub async fn handle_claim_keep_click(
+	ctx: &poise::serenity_prelude::Context,
+	app: &AppState,
+	interaction: &ComponentInteraction,
+) -> Result<(), Error> {
+	claim::handle_keep_click(ctx, app, interaction).await
+}
 
-	Ok(())
+/// Manages the temporary voice rooms of the join-to-create lobby.
+pub async fn handle_voice_state_update(
+	ctx: &poise::serenity_prelude::Context,
+	app: &AppState,
+	old: Option<&VoiceState>,
+	new: &VoiceState,
+) -> Result<(), Error> {
+	voice::handle_voice_state_update(ctx, app, old, new).await
+}
+
+/// Audit details of an ownership change, so the trail shows who owned a channel before.
+fn owner_change(previous: Option<&ChannelOwnership>, owner: UserId) -> [(&'static str, String); 2] {
+	let previous = match previous {
+		Some(previous) => previous.owner.mention().to_string(),
+		None => "niemand".to_string(),
+	};
+	[
+		("Vorheriger Besitzer", previous),
+		("Neuer Besitzer", owner.mention().to_string()),
+	]
+}
+
+/// Whether a request failed because the resource no longer exists, as opposed to a transient error.
+fn is_not_found(err: &serenity::Error) -> bool {
+	matches!(
+		err,
+		serenity::Error::Http(HttpError::UnsuccessfulRequest(ErrorResponse {
+			status_code: StatusCode::NOT_FOUND,
+			..
+		}))
+	)
+}
+
+/// Discord rejects empty embed fields, so channels without topic need a placeholder.
+fn topic_or_placeholder(channel: &GuildChannel) -> &str {
+	match channel.topic.as_deref() {
+		Some(topic) if !topic.is_empty() => topic,
+		_ => "-",
+	}
+}
+
+fn remove_meta(str: &str) -> String {
+	let mut lines = str.trim().lines().collect::<Vec<_>>();
+	lines.truncate(lines.len() - 1);
+	lines.join("\n").trim().to_string()
 }
 
-/// Tracks channel meta data like ownership.
+/// Ownership meta data as it was stored by older versions in the last line of the channel topic. Only used for
+/// importing into the database.
 #[derive(Serialize, Deserialize, Debug)]
 struct ChannelMeta {
 	#[serde(with = "channel_meta_serde")]
@@ -0,0 +1,86 @@
+use poise::serenity_prelude::{
+	ChannelId,
+	PermissionOverwrite,
+	UserId,
+};
+use rusqlite::OptionalExtension;
+
+use crate::{
+	database::Database,
+	Error,
+};
+
+/// A self-managed channel which has been moved into the archive. Keeps everything needed to restore it.
+#[derive(Debug)]
+pub struct ArchivedChannel {
+	pub channel: ChannelId,
+	/// Category the channel was in before it was archived.
+	pub category: ChannelId,
+	/// Permission overwrites before the channel was locked.
+	pub permission_overwrites: Vec<PermissionOverwrite>,
+	pub archived_by: UserId,
+	pub archived_at: i64,
+}
+
+impl ArchivedChannel {
+	pub fn load(db: &Database, channel: ChannelId) -> Result<Option<Self>, Error> {
+		let row = db
+			.lock()
+			.query_row(
+				"SELECT category_id, permission_overwrites, archived_by, archived_at FROM archived_channels WHERE channel_id = ?1",
+				[channel.get() as i64],
+				|row| {
+					Ok((
+						row.get::<_, i64>(0)?,
+						row.get::<_, String>(1)?,
+						row.get::<_, i64>(2)?,
+						row.get::<_, i64>(3)?,
+					))
+				},
+			)
+			.optional()?;
+
+		let Some((category, permission_overwrites, archived_by, archived_at)) = row else {
+			return Ok(None);
+		};
+
+		Ok(Some(ArchivedChannel {
+			channel,
+			category: ChannelId::new(category as u64),
+			permission_overwrites: serde_json::from_str(&permission_overwrites)?,
+			archived_by: UserId::new(archived_by as u64),
+			archived_at,
+		}))
+	}
+
+	pub fn insert(&self, db: &Database) -> Result<(), Error> {
+		db.lock().execute(
+			"INSERT OR REPLACE INTO archived_channels (channel_id, category_id, permission_overwrites, archived_by, archived_at) \
+			 VALUES (?1, ?2, ?3, ?4, ?5)",
+			(
+				self.channel.get() as i64,
+				self.category.get() as i64,
+				serde_json::to_string(&self.permission_overwrites)?,
+				self.archived_by.get() as i64,
+				self.archived_at,
+			),
+		)?;
+		Ok(())
+	}
+
+	pub fn delete(db: &Database, channel: ChannelId) -> Result<(), Error> {
+		db.lock()
+			.execute("DELETE FROM archived_channels WHERE channel_id = ?1", [channel.get() as i64])?;
+		Ok(())
+	}
+
+	/// Returns all channels which have been archived before the given point in time.
+	pub fn archived_before(db: &Database, time: i64) -> Result<Vec<ChannelId>, Error> {
+		let db = db.lock();
+		let mut statement = db.prepare("SELECT channel_id FROM archived_channels WHERE archived_at < ?1")?;
+		let channels = statement
+			.query_map([time], |row| Ok(ChannelId::new(row.get::<_, i64>(0)? as u64)))?
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(channels)
+	}
+}
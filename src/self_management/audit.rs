@@ -0,0 +1,195 @@
+use poise::serenity_prelude::{
+	ChannelId,
+	GuildChannel,
+	Mentionable,
+	UserId,
+};
+use serde::{
+	Deserialize,
+	Serialize,
+};
+
+use crate::{
+	database::{
+		self,
+		Database,
+	},
+	Error,
+};
+
+/// State of a channel at the time of an audit event, limited to what users can change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelSnapshot {
+	pub name: String,
+	pub topic: Option<String>,
+	pub nsfw: bool,
+	pub slowmode: Option<u16>,
+	pub category: Option<ChannelId>,
+}
+
+impl From<&GuildChannel> for ChannelSnapshot {
+	fn from(channel: &GuildChannel) -> Self {
+		ChannelSnapshot {
+			name: channel.name.clone(),
+			topic: channel.topic.clone().filter(|topic| !topic.is_empty()),
+			nsfw: channel.nsfw,
+			slowmode: channel.rate_limit_per_user.filter(|slowmode| *slowmode > 0),
+			category: channel.parent_id,
+		}
+	}
+}
+
+impl ChannelSnapshot {
+	/// Human readable values of all fields, in the order they are displayed.
+	fn fields(&self) -> [(&'static str, String); 5] {
+		[
+			("Name", self.name.clone()),
+			("Beschreibung", self.topic.clone().unwrap_or_default()),
+			("NSFW", if self.nsfw { "ja" } else { "nein" }.to_string()),
+			(
+				"Slowmode",
+				self.slowmode.map(|slowmode| format!("{}s", slowmode)).unwrap_or_default(),
+			),
+			(
+				"Kategorie",
+				self
+					.category
+					.map(|category| category.mention().to_string())
+					.unwrap_or_default(),
+			),
+		]
+	}
+
+	/// Returns all fields that differ between both snapshots as name, old value and new value.
+	pub fn changes(&self, other: &ChannelSnapshot) -> Vec<(&'static str, String, String)> {
+		self
+			.fields()
+			.into_iter()
+			.zip(other.fields())
+			.filter(|((_, before), (_, after))| before != after)
+			.map(|((name, before), (_, after))| (name, before, after))
+			.collect()
+	}
+}
+
+/// Persisted record of a self-management action, so moderators can reconstruct the history of a channel.
+#[derive(Debug)]
+pub struct AuditEvent {
+	pub channel: ChannelId,
+	/// User who performed the action, `None` for actions of the bot itself.
+	pub actor: Option<UserId>,
+	pub action: String,
+	pub before: Option<ChannelSnapshot>,
+	pub after: Option<ChannelSnapshot>,
+	pub details: Vec<(String, String)>,
+	pub created_at: i64,
+}
+
+impl AuditEvent {
+	pub fn new(
+		channel: ChannelId,
+		actor: Option<UserId>,
+		action: &str,
+		before: Option<&GuildChannel>,
+		after: Option<&GuildChannel>,
+		details: &[(&str, String)],
+	) -> Self {
+		AuditEvent {
+			channel,
+			actor,
+			action: action.to_string(),
+			before: before.map(ChannelSnapshot::from),
+			after: after.map(ChannelSnapshot::from),
+			details: details
+				.iter()
+				.map(|(name, value)| (name.to_string(), value.clone()))
+				.collect(),
+			created_at: database::now(),
+		}
+	}
+
+	pub fn insert(&self, db: &Database) -> Result<(), Error> {
+		let before = self.before.as_ref().map(serde_json::to_string).transpose()?;
+		let after = self.after.as_ref().map(serde_json::to_string).transpose()?;
+		db.lock().execute(
+			"INSERT INTO audit_events (channel_id, actor_id, action, before, after, details, created_at) VALUES (?1, ?2, ?3, ?4, ?5, \
+			 ?6, ?7)",
+			(
+				self.channel.get() as i64,
+				self.actor.map(|actor| actor.get() as i64),
+				&self.action,
+				before,
+				after,
+				serde_json::to_string(&self.details)?,
+				self.created_at,
+			),
+		)?;
+		Ok(())
+	}
+
+	/// Returns the history of the channel, latest events first.
+	pub fn for_channel(db: &Database, channel: ChannelId) -> Result<Vec<Self>, Error> {
+		let db = db.lock();
+		let mut statement = db.prepare(
+			"SELECT actor_id, action, before, after, details, created_at FROM audit_events WHERE channel_id = ?1 ORDER BY id DESC",
+		)?;
+		let rows = statement
+			.query_map([channel.get() as i64], |row| {
+				Ok((
+					row.get::<_, Option<i64>>(0)?,
+					row.get::<_, String>(1)?,
+					row.get::<_, Option<String>>(2)?,
+					row.get::<_, Option<String>>(3)?,
+					row.get::<_, String>(4)?,
+					row.get::<_, i64>(5)?,
+				))
+			})?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		rows
+			.into_iter()
+			.map(|(actor, action, before, after, details, created_at)| {
+				Ok(AuditEvent {
+					channel,
+					actor: actor.map(|actor| UserId::new(actor as u64)),
+					action,
+					before: before.map(|before| serde_json::from_str(&before)).transpose()?,
+					after: after.map(|after| serde_json::from_str(&after)).transpose()?,
+					details: serde_json::from_str(&details)?,
+					created_at,
+				})
+			})
+			.collect()
+	}
+
+	/// Summary of the event for an embed field, consisting of name and value.
+	pub fn describe(&self) -> (String, String) {
+		let actor = match self.actor {
+			Some(actor) => actor.mention().to_string(),
+			None => "Bot".to_string(),
+		};
+		let mut lines = vec![format!("<t:{}:f> von {}", self.created_at, actor)];
+
+		match (&self.before, &self.after) {
+			(Some(before), Some(after)) => {
+				for (name, before, after) in before.changes(after) {
+					lines.push(format!("{}: `{}` → `{}`", name, before, after));
+				}
+			},
+			// creation and deletion show the whole state
+			(Some(snapshot), None) | (None, Some(snapshot)) => {
+				for (name, value) in snapshot.fields() {
+					if !value.is_empty() {
+						lines.push(format!("{}: `{}`", name, value));
+					}
+				}
+			},
+			(None, None) => {},
+		}
+		for (name, value) in &self.details {
+			lines.push(format!("{}: {}", name, value));
+		}
+
+		(self.action.clone(), lines.join("\n"))
+	}
+}
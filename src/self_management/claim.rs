@@ -0,0 +1,278 @@
+#[allow(unused_imports)]
+use log::{
+	debug,
+	error,
+	info,
+	trace,
+	warn,
+};
+use poise::serenity_prelude::{
+	ButtonStyle,
+	ChannelId,
+	ComponentInteraction,
+	Context,
+	CreateActionRow,
+	CreateButton,
+	CreateInteractionResponse,
+	CreateInteractionResponseMessage,
+	EditMessage,
+	GuildChannel,
+	Mentionable,
+	MessageId,
+	UserId,
+};
+use rusqlite::OptionalExtension;
+
+use super::{
+	is_not_found,
+	log_both_as,
+	owner_change,
+	ownership::ChannelOwnership,
+	privacy,
+	MemberPolicy,
+};
+use crate::{
+	database::{
+		self,
+		Database,
+	},
+	AppState,
+	Error,
+};
+
+/// Claim of an abandoned channel, which the current owner can still object to until the deadline has passed.
+#[derive(Debug)]
+pub struct PendingClaim {
+	pub channel: ChannelId,
+	pub claimant: UserId,
+	/// Message in the channel which notified the owner.
+	pub message: MessageId,
+	/// Last message before the notification, unknown for claims made by older versions.
+	pub previous_message: Option<MessageId>,
+	pub deadline: i64,
+}
+
+impl PendingClaim {
+	pub fn load(db: &Database, channel: ChannelId) -> Result<Option<Self>, Error> {
+		let claim = db
+			.lock()
+			.query_row(
+				"SELECT claimant_id, message_id, previous_message_id, deadline FROM pending_claims WHERE channel_id = ?1",
+				[channel.get() as i64],
+				|row| {
+					Ok(PendingClaim {
+						channel,
+						claimant: UserId::new(row.get::<_, i64>(0)? as u64),
+						message: MessageId::new(row.get::<_, i64>(1)? as u64),
+						previous_message: row.get::<_, Option<i64>>(2)?.map(|id| MessageId::new(id as u64)),
+						deadline: row.get(3)?,
+					})
+				},
+			)
+			.optional()?;
+		Ok(claim)
+	}
+
+	pub fn insert(&self, db: &Database) -> Result<(), Error> {
+		db.lock().execute(
+			"INSERT INTO pending_claims (channel_id, claimant_id, message_id, previous_message_id, deadline) VALUES (?1, ?2, ?3, ?4, \
+			 ?5)",
+			(
+				self.channel.get() as i64,
+				self.claimant.get() as i64,
+				self.message.get() as i64,
+				self.previous_message.map(|id| id.get() as i64),
+				self.deadline,
+			),
+		)?;
+		Ok(())
+	}
+
+	pub fn delete(db: &Database, channel: ChannelId) -> Result<(), Error> {
+		db.lock()
+			.execute("DELETE FROM pending_claims WHERE channel_id = ?1", [channel.get() as i64])?;
+		Ok(())
+	}
+
+	/// Returns all claims whose objection period has ended.
+	pub fn due(db: &Database, now: i64) -> Result<Vec<Self>, Error> {
+		let db = db.lock();
+		let mut statement = db.prepare(
+			"SELECT channel_id, claimant_id, message_id, previous_message_id, deadline FROM pending_claims WHERE deadline <= ?1",
+		)?;
+		let claims = statement
+			.query_map([now], |row| {
+				Ok(PendingClaim {
+					channel: ChannelId::new(row.get::<_, i64>(0)? as u64),
+					claimant: UserId::new(row.get::<_, i64>(1)? as u64),
+					message: MessageId::new(row.get::<_, i64>(2)? as u64),
+					previous_message: row.get::<_, Option<i64>>(3)?.map(|id| MessageId::new(id as u64)),
+					deadline: row.get(4)?,
+				})
+			})?
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(claims)
+	}
+}
+
+/// Components attached to the claim notification, allowing the owner to keep their channel.
+pub fn keep_button(channel: ChannelId) -> Vec<CreateActionRow> {
+	let button = CreateButton::new(format!("claim_keep:{}", channel))
+		.label("Behalten")
+		.style(ButtonStyle::Primary);
+	vec![CreateActionRow::Buttons(vec![button])]
+}
+
+/// Handles the owner objecting to a pending claim.
+pub async fn handle_keep_click(ctx: &Context, app: &AppState, interaction: &ComponentInteraction) -> Result<(), Error> {
+	let custom_id = interaction.data.custom_id.as_str();
+	let channel_id = custom_id
+		.split_once(':')
+		.and_then(|(_, id)| id.parse::<u64>().ok())
+		.map(ChannelId::new)
+		.ok_or(format!("Unknown format in claim custom_id: {}", custom_id))?;
+
+	let respond = |content: String| {
+		CreateInteractionResponse::Message(CreateInteractionResponseMessage::new().content(content).ephemeral(true))
+	};
+
+	let Some(claim) = PendingClaim::load(&app.db, channel_id)? else {
+		interaction
+			.create_response(ctx, respond("Für diesen Kanal läuft keine Übernahme mehr.".to_string()))
+			.await?;
+		return Ok(());
+	};
+
+	// only the people currently in charge may object
+	let ownership = ChannelOwnership::load(&app.db, channel_id)?;
+	let user = &interaction.user;
+	let is_owner = ownership.is_some_and(|o| o.owner == user.id || o.co_owners.contains(&user.id));
+	if !is_owner {
+		interaction
+			.create_response(ctx, respond("Nur der Besitzer kann der Übernahme widersprechen.".to_string()))
+			.await?;
+		return Ok(());
+	}
+
+	PendingClaim::delete(&app.db, channel_id)?;
+	interaction
+		.create_response(
+			ctx,
+			CreateInteractionResponse::UpdateMessage(
+				CreateInteractionResponseMessage::new()
+					.content(format!(
+						"{} hat der Übernahme durch {} widersprochen und bleibt Besitzer dieses Kanals.",
+						user.mention(),
+						claim.claimant.mention()
+					))
+					.components(vec![]),
+			),
+		)
+		.await?;
+
+	if let Some(channel) = channel_id.to_channel(ctx).await?.guild() {
+		log_both_as(ctx, app, Some(user), "Kanalübernahme abgelehnt", None, Some(&channel), &[(
+			"Antragsteller",
+			claim.claimant.mention().to_string(),
+		)])
+		.await?;
+	}
+
+	Ok(())
+}
+
+/// Transfers ownership for all claims whose objection period ended without objection. Claims which fail to be
+/// finalized are kept and retried next time.
+pub async fn finalize_claims(ctx: &Context, app: &AppState) -> Result<(), Error> {
+	for claim in PendingClaim::due(&app.db, database::now())? {
+		if let Err(err) = finalize_claim(ctx, app, &claim).await {
+			warn!(
+				"Failed to finalize claim of channel {} by {}: {}",
+				claim.channel, claim.claimant, err
+			);
+		}
+	}
+
+	Ok(())
+}
+
+async fn finalize_claim(ctx: &Context, app: &AppState, claim: &PendingClaim) -> Result<(), Error> {
+	let channel = match claim.channel.to_channel(ctx).await {
+		Ok(channel) => channel.guild(),
+		// channel has vanished in the meantime, so there is nothing left to claim
+		Err(err) if is_not_found(&err) => None,
+		Err(err) => return Err(err.into()),
+	};
+	let Some(channel) = channel else {
+		warn!("Dropping claim for channel {}, since it no longer exists", claim.channel);
+		PendingClaim::delete(&app.db, claim.channel)?;
+		return Ok(());
+	};
+
+	let member = match channel.guild_id.member(ctx, claim.claimant).await {
+		Ok(member) => Some(member),
+		// claimant has left the server in the meantime
+		Err(err) if is_not_found(&err) => None,
+		Err(err) => return Err(err.into()),
+	};
+
+	// claimant might have reached their limit or lost their permission while waiting for objections
+	let rejection = match &member {
+		Some(member) => MemberPolicy::check(ctx, app, member)
+			.await?
+			.map(|violation| violation.about(member)),
+		None => Some(format!("{} ist nicht mehr auf dem Server.", claim.claimant.mention())),
+	};
+	if let Some(rejection) = rejection {
+		info!(
+			"Dropping claim for channel {} ({}) by {}, since they may not own another channel",
+			channel.name, channel.id, claim.claimant
+		);
+		PendingClaim::delete(&app.db, channel.id)?;
+		update_notification(ctx, &channel, claim, format!("{} Die Übernahme ist gescheitert.", rejection)).await;
+		log_both_as(ctx, app, None, "Kanalübernahme gescheitert", None, Some(&channel), &[(
+			"Antragsteller",
+			claim.claimant.mention().to_string(),
+		)])
+		.await?;
+		return Ok(());
+	}
+
+	info!(
+		"Transferring channel {} ({}) to claimant {}",
+		channel.name, channel.id, claim.claimant
+	);
+	let previous = ChannelOwnership::load(&app.db, channel.id)?;
+	privacy::grant_access(ctx, &channel, claim.claimant).await?;
+	ChannelOwnership::set_owner(&app.db, channel.id, claim.claimant)?;
+	PendingClaim::delete(&app.db, channel.id)?;
+
+	let content = format!("{} ist nun der neue Besitzer dieses Kanals.", claim.claimant.mention());
+	update_notification(ctx, &channel, claim, content).await;
+
+	let claimant = member.map(|member| member.user);
+	log_both_as(
+		ctx,
+		app,
+		claimant.as_ref(),
+		"Kanal übernommen",
+		None,
+		Some(&channel),
+		&owner_change(previous.as_ref(), claim.claimant),
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// Replaces the claim notification with the outcome of the claim.
+async fn update_notification(ctx: &Context, channel: &GuildChannel, claim: &PendingClaim, content: String) {
+	let edit = channel
+		.id
+		.edit_message(ctx, claim.message, EditMessage::default().content(content).components(vec![]))
+		.await;
+	if let Err(err) = edit {
+		// notification might have been deleted, which is no reason to fail the claim
+		warn!("Failed to update claim notification in channel {}: {}", channel.id, err);
+	}
+}
@@ -0,0 +1,285 @@
+use std::{
+	collections::{
+		HashMap,
+		VecDeque,
+	},
+	sync::Mutex,
+	time::Duration,
+};
+
+#[allow(unused_imports)]
+use log::{
+	debug,
+	error,
+	info,
+	trace,
+	warn,
+};
+use poise::serenity_prelude::{
+	ChannelId,
+	Context,
+	CreateInteractionResponseFollowup,
+	EditChannel,
+	Mentionable,
+	User,
+};
+use serenity::builder::Builder;
+use tokio::time::Instant;
+
+use super::{
+	can_edit_channel,
+	check_channel_name_as,
+	is_self_managed,
+	log_both_as,
+	ownership::ChannelOwnership,
+	sorting,
+};
+use crate::{
+	database,
+	AppState,
+	Error,
+};
+
+/// Discord only allows two renames or topic changes per channel every ten minutes. Staying within this budget ourselves
+/// prevents edits from silently piling up in the HTTP rate limiter.
+const EDITS_PER_WINDOW: usize = 2;
+const EDIT_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Changes to a self-managed channel. Unset fields are left untouched.
+#[derive(Debug, Default, Clone)]
+pub struct ChannelEdit {
+	pub name: Option<String>,
+	pub topic: Option<String>,
+	pub nsfw: Option<bool>,
+}
+
+impl ChannelEdit {
+	/// Merges a later edit into this one, the later values win.
+	fn merge(&mut self, other: ChannelEdit) {
+		self.name = other.name.or(self.name.take());
+		self.topic = other.topic.or(self.topic.take());
+		self.nsfw = other.nsfw.or(self.nsfw);
+	}
+}
+
+/// User waiting for an edit, who is notified via the follow-up of their interaction once it has been applied.
+struct Requester {
+	user: User,
+	interaction_token: Option<String>,
+}
+
+#[derive(Default)]
+struct ChannelQueue {
+	/// Edits waiting to be applied, in the order they were requested.
+	pending: Vec<(ChannelEdit, Requester)>,
+	/// Points in time of the edits within the current window, oldest first.
+	recent: VecDeque<Instant>,
+}
+
+impl ChannelQueue {
+	fn ready_at(&mut self, now: Instant) -> Instant {
+		while self
+			.recent
+			.front()
+			.is_some_and(|edit| now.duration_since(*edit) >= EDIT_WINDOW)
+		{
+			self.recent.pop_front();
+		}
+
+		match self.recent.len() < EDITS_PER_WINDOW {
+			true => now,
+			false => self.recent[0] + EDIT_WINDOW,
+		}
+	}
+}
+
+/// Per-channel queue for edits of self-managed channels, which coalesces changes until the rate limit allows applying
+/// them.
+#[derive(Default)]
+pub struct EditQueue {
+	channels: Mutex<HashMap<ChannelId, ChannelQueue>>,
+}
+
+impl EditQueue {
+	/// Queues the edit and returns the unix timestamp at which it will be applied. The requester is notified via a
+	/// follow-up to their interaction afterwards, if a token is given.
+	pub fn enqueue(
+		&self,
+		ctx: &Context,
+		app: &AppState,
+		channel: ChannelId,
+		edit: ChannelEdit,
+		user: User,
+		interaction_token: Option<String>,
+	) -> i64 {
+		let now = Instant::now();
+		let mut channels = self.channels.lock().expect("edit queue lock poisoned");
+		channels.retain(|_, queue| !queue.pending.is_empty() || !queue.recent.is_empty());
+
+		let queue = channels.entry(channel).or_default();
+		let ready_at = queue.ready_at(now);
+		let requester = Requester {
+			user,
+			interaction_token,
+		};
+
+		// otherwise a worker is already waiting for this channel
+		if queue.pending.is_empty() {
+			tokio::spawn(run(ctx.clone(), app.clone(), channel, ready_at));
+		}
+		queue.pending.push((edit, requester));
+
+		database::now() + ready_at.duration_since(now).as_secs() as i64
+	}
+
+	fn take(&self, channel: ChannelId) -> Vec<(ChannelEdit, Requester)> {
+		let mut channels = self.channels.lock().expect("edit queue lock poisoned");
+		let queue = channels.entry(channel).or_default();
+		queue.recent.push_back(Instant::now());
+		std::mem::take(&mut queue.pending)
+	}
+}
+
+async fn run(ctx: Context, app: AppState, channel: ChannelId, ready_at: Instant) {
+	tokio::time::sleep_until(ready_at).await;
+
+	let edits = app.edit_queue.take(channel);
+	if edits.is_empty() {
+		return;
+	}
+	debug!("Applying {} queued edit(s) to channel {}", edits.len(), channel);
+
+	let outcomes = match apply(&ctx, &app, channel, &edits).await {
+		Ok(outcomes) => outcomes,
+		Err(err) => {
+			error!("Failed to apply queued edit to channel {}: {}", channel, err);
+			let content = format!(
+				"Ich konnte den Channel {} leider nicht modifizieren: {}",
+				channel.mention(),
+				err
+			);
+			vec![content; edits.len()]
+		},
+	};
+
+	for ((_, requester), content) in edits.iter().zip(outcomes) {
+		let Some(token) = &requester.interaction_token else {
+			continue;
+		};
+		let followup = CreateInteractionResponseFollowup::new().content(content).ephemeral(true);
+		// interaction tokens expire after 15 minutes, which long waits might exceed
+		if let Err(err) = followup.execute(&ctx, (None, token)).await {
+			warn!(
+				"Failed to notify {} about edit of channel {}: {}",
+				requester.user.name, channel, err
+			);
+		}
+	}
+}
+
+/// Applies all edits whose requesters may still edit the channel and returns the outcome for each requester.
+async fn apply(
+	ctx: &Context,
+	app: &AppState,
+	channel: ChannelId,
+	edits: &[(ChannelEdit, Requester)],
+) -> Result<Vec<String>, Error> {
+	let before = channel
+		.to_channel(ctx)
+		.await?
+		.guild()
+		.ok_or("channel is not a guild channel")?;
+
+	// the channel might have been archived or moved out of the self-managed area while waiting
+	let config = app.config();
+	if !is_self_managed(&before, &config.self_managment) {
+		let discarded = format!(
+			"{} wird nicht mehr selbst verwaltet, deine Änderung wurde verworfen.",
+			channel.mention()
+		);
+		return Ok(vec![discarded; edits.len()]);
+	}
+
+	// ownership might have changed while waiting, like the channel being transferred or claimed
+	let ownership = ChannelOwnership::load(&app.db, channel)?;
+	let mut edit = ChannelEdit::default();
+	let mut requesters = Vec::new();
+	let allowed = edits
+		.iter()
+		.map(|(requested, requester)| {
+			let allowed = can_edit_channel(
+				&app.db,
+				&requester.user.id,
+				&before,
+				ownership.as_ref(),
+				&config.self_managment,
+			)?;
+			if allowed {
+				edit.merge(requested.clone());
+				requesters.push(requester);
+			}
+			Ok(allowed)
+		})
+		.collect::<Result<Vec<_>, Error>>()?;
+	let outcomes = |content: String| {
+		let denied = format!(
+			"Du darfst {} nicht mehr bearbeiten, deine Änderung wurde verworfen.",
+			channel.mention()
+		);
+		allowed
+			.iter()
+			.map(|allowed| if *allowed { content.clone() } else { denied.clone() })
+			.collect::<Vec<_>>()
+	};
+	let Some(last) = requesters.last() else {
+		return Ok(outcomes(String::new()));
+	};
+
+	// names are validated again, since another channel might have taken the name or the rules changed in the meantime
+	if let Some(name) = &edit.name {
+		if let Err(err) = check_channel_name_as(ctx, app, &last.user, before.guild_id, name, Some(channel)).await {
+			return Ok(outcomes(format!(
+				"Ich konnte den Channel {} leider nicht modifizieren: {}",
+				channel.mention(),
+				err
+			)));
+		}
+	}
+
+	let mut edit_channel = EditChannel::default();
+	if let Some(name) = &edit.name {
+		edit_channel = edit_channel.name(name);
+	}
+	if let Some(topic) = &edit.topic {
+		edit_channel = edit_channel.topic(topic);
+	}
+	if let Some(nsfw) = edit.nsfw {
+		edit_channel = edit_channel.nsfw(nsfw);
+	}
+	let after = channel.edit(ctx, edit_channel).await?;
+
+	// coalesced edits might stem from several people, but the log entry is attributed to the last one
+	let mut details = Vec::new();
+	if requesters.len() > 1 {
+		let users = requesters
+			.iter()
+			.map(|requester| requester.user.mention().to_string())
+			.collect::<Vec<_>>();
+		details.push(("Beteiligte", users.join(", ")));
+	}
+	log_both_as(
+		ctx,
+		app,
+		requesters.last().map(|requester| &requester.user),
+		"Channel aktualisiert",
+		Some(&before),
+		Some(&after),
+		&details,
+	)
+	.await?;
+
+	// sort channel list to maintain peace and harmony
+	sorting::request_sort(app);
+
+	Ok(outcomes(format!("Ich hab den Channel modifiziert: {}", after.name)))
+}
@@ -0,0 +1,135 @@
+#[allow(unused_imports)]
+use log::{
+	debug,
+	error,
+	info,
+	trace,
+	warn,
+};
+use poise::serenity_prelude::{
+	ChannelId,
+	Context,
+	Mentionable,
+	PermissionOverwriteType,
+	UserId,
+};
+
+use super::{
+	is_not_found,
+	log_detailed,
+	privacy,
+	MUTED_PERMISSIONS,
+};
+use crate::{
+	database::{
+		self,
+		Database,
+	},
+	AppState,
+	Error,
+};
+
+/// User who may not write in a self-managed channel until the mute expires.
+#[derive(Debug)]
+pub struct ChannelMute {
+	pub channel: ChannelId,
+	pub user: UserId,
+	pub muted_by: UserId,
+	pub expires_at: i64,
+}
+
+impl ChannelMute {
+	/// Replaces an existing mute of the same user, so mutes can be extended or shortened.
+	pub fn insert(&self, db: &Database) -> Result<(), Error> {
+		db.lock().execute(
+			"INSERT OR REPLACE INTO channel_mutes (channel_id, user_id, muted_by, expires_at) VALUES (?1, ?2, ?3, ?4)",
+			(
+				self.channel.get() as i64,
+				self.user.get() as i64,
+				self.muted_by.get() as i64,
+				self.expires_at,
+			),
+		)?;
+		Ok(())
+	}
+
+	/// Returns `false` if the user was not muted.
+	pub fn delete(db: &Database, channel: ChannelId, user: UserId) -> Result<bool, Error> {
+		let deleted = db.lock().execute(
+			"DELETE FROM channel_mutes WHERE channel_id = ?1 AND user_id = ?2",
+			(channel.get() as i64, user.get() as i64),
+		)?;
+		Ok(deleted > 0)
+	}
+
+	pub fn is_muted(db: &Database, channel: ChannelId, user: UserId) -> Result<bool, Error> {
+		let count: i64 = db.lock().query_row(
+			"SELECT COUNT(*) FROM channel_mutes WHERE channel_id = ?1 AND user_id = ?2",
+			(channel.get() as i64, user.get() as i64),
+			|row| row.get(0),
+		)?;
+		Ok(count > 0)
+	}
+
+	pub fn delete_channel(db: &Database, channel: ChannelId) -> Result<(), Error> {
+		db.lock()
+			.execute("DELETE FROM channel_mutes WHERE channel_id = ?1", [channel.get() as i64])?;
+		Ok(())
+	}
+
+	/// Returns all mutes which have expired at the given point in time.
+	pub fn expired(db: &Database, now: i64) -> Result<Vec<Self>, Error> {
+		let db = db.lock();
+		let mut statement =
+			db.prepare("SELECT channel_id, user_id, muted_by, expires_at FROM channel_mutes WHERE expires_at <= ?1")?;
+		let mutes = statement
+			.query_map([now], |row| {
+				Ok(ChannelMute {
+					channel: ChannelId::new(row.get::<_, i64>(0)? as u64),
+					user: UserId::new(row.get::<_, i64>(1)? as u64),
+					muted_by: UserId::new(row.get::<_, i64>(2)? as u64),
+					expires_at: row.get(3)?,
+				})
+			})?
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(mutes)
+	}
+}
+
+/// Lifts all mutes whose time is up. Mutes which fail to be lifted are kept and retried next time.
+pub async fn lift_expired_mutes(ctx: &Context, app: &AppState) -> Result<(), Error> {
+	for mute in ChannelMute::expired(&app.db, database::now())? {
+		if let Err(err) = lift_mute(ctx, app, &mute).await {
+			warn!("Failed to lift mute of {} in channel {}: {}", mute.user, mute.channel, err);
+		}
+	}
+
+	Ok(())
+}
+
+async fn lift_mute(ctx: &Context, app: &AppState, mute: &ChannelMute) -> Result<(), Error> {
+	let channel = match mute.channel.to_channel(ctx).await {
+		Ok(channel) => channel.guild(),
+		// channel has vanished in the meantime, so there is nothing left to lift
+		Err(err) if is_not_found(&err) => None,
+		Err(err) => return Err(err.into()),
+	};
+	let Some(mut channel) = channel else {
+		warn!("Dropping mute in channel {}, since it no longer exists", mute.channel);
+		ChannelMute::delete(&app.db, mute.channel, mute.user)?;
+		return Ok(());
+	};
+
+	debug!("Lifting mute of {} in channel {} ({})", mute.user, channel.name, channel.id);
+	let member = PermissionOverwriteType::Member(mute.user);
+	privacy::set_permissions(ctx, &mut channel, member, MUTED_PERMISSIONS, None).await?;
+	ChannelMute::delete(&app.db, mute.channel, mute.user)?;
+
+	log_detailed(ctx, app, None, "Stummschaltung abgelaufen", &channel, &[(
+		"Nutzer",
+		mute.user.mention().to_string(),
+	)])
+	.await?;
+
+	Ok(())
+}
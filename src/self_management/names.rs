@@ -0,0 +1,88 @@
+use poise::serenity_prelude::{
+	ChannelId,
+	GuildChannel,
+};
+
+use crate::config::ChannelNames;
+
+/// Normalizes a channel name the same way Discord does for text channels, so names can be compared.
+pub fn normalize(name: &str) -> String {
+	name.trim().to_lowercase().split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+/// Reason why a channel name was rejected.
+pub struct Rejection {
+	/// Explanation for the user.
+	pub message: String,
+	/// Internal explanation for the detailed log, might contain information which should not be shown to users.
+	pub detail: String,
+}
+
+impl Rejection {
+	fn new(message: String) -> Self {
+		Rejection {
+			detail: message.clone(),
+			message,
+		}
+	}
+}
+
+/// Checks if the name may be used for a self-managed channel.
+///
+/// `channels` has to contain all channels of the guild, `channel` is the channel being renamed, if any.
+pub fn validate(
+	name: &str,
+	config: Option<&ChannelNames>,
+	channels: &[GuildChannel],
+	is_self_managed: impl Fn(&GuildChannel) -> bool,
+	channel: Option<ChannelId>,
+) -> Result<(), Rejection> {
+	let normalized = normalize(name);
+	if normalized.is_empty() {
+		return Err(Rejection::new("Der Kanalname darf nicht leer sein.".to_string()));
+	}
+
+	if let Some(config) = config {
+		let length = normalized.chars().count();
+		if length > config.max_length {
+			return Err(Rejection::new(format!(
+				"Der Kanalname ist {} Zeichen lang, erlaubt sind höchstens {}.",
+				length, config.max_length
+			)));
+		}
+
+		if config.reserved.iter().any(|reserved| normalize(reserved) == normalized) {
+			return Err(Rejection::new(format!("Der Kanalname `{}` ist reserviert.", normalized)));
+		}
+
+		if let Some(pattern) = config.blocklist.iter().find(|pattern| pattern.is_match(&normalized)) {
+			return Err(Rejection {
+				message: format!("Der Kanalname `{}` ist nicht erlaubt.", normalized),
+				detail: format!("Der Kanalname `{}` passt auf die Sperrregel `{}`.", normalized, pattern),
+			});
+		}
+	}
+
+	// names must neither be confused with official channels nor with other self-managed ones
+	let duplicate = channels
+		.iter()
+		.filter(|c| Some(c.id) != channel)
+		.find(|c| normalize(&c.name) == normalized);
+	if let Some(duplicate) = duplicate {
+		return Err(Rejection::new(
+			if is_self_managed(duplicate) {
+				format!(
+					"Es gibt bereits einen selbstverwalteten Kanal mit dem Namen `{}`.",
+					normalized
+				)
+			} else {
+				format!(
+					"Der Kanalname `{}` ist bereits für einen offiziellen Kanal vergeben.",
+					normalized
+				)
+			},
+		));
+	}
+
+	Ok(())
+}
@@ -0,0 +1,125 @@
+use poise::serenity_prelude::{
+	ChannelId,
+	UserId,
+};
+use rusqlite::OptionalExtension;
+
+use crate::{
+	database::{
+		self,
+		Database,
+	},
+	Error,
+};
+
+/// Ownership information of a self-managed channel, as stored in the database.
+#[derive(Debug, Clone)]
+pub struct ChannelOwnership {
+	pub channel: ChannelId,
+	pub owner: UserId,
+	pub creator: UserId,
+	pub created_at: i64,
+	pub co_owners: Vec<UserId>,
+}
+
+impl ChannelOwnership {
+	pub fn new(channel: ChannelId, owner: UserId) -> Self {
+		ChannelOwnership {
+			channel,
+			owner,
+			creator: owner,
+			created_at: database::now(),
+			co_owners: Vec::new(),
+		}
+	}
+
+	pub fn load(db: &Database, channel: ChannelId) -> Result<Option<Self>, Error> {
+		let db = db.lock();
+		let ownership = db
+			.query_row(
+				"SELECT owner_id, creator_id, created_at FROM channels WHERE channel_id = ?1",
+				[channel.get() as i64],
+				|row| {
+					Ok(ChannelOwnership {
+						channel,
+						owner: UserId::new(row.get::<_, i64>(0)? as u64),
+						creator: UserId::new(row.get::<_, i64>(1)? as u64),
+						created_at: row.get(2)?,
+						co_owners: Vec::new(),
+					})
+				},
+			)
+			.optional()?;
+
+		let Some(mut ownership) = ownership else {
+			return Ok(None);
+		};
+
+		let mut statement = db.prepare("SELECT user_id FROM channel_co_owners WHERE channel_id = ?1 ORDER BY rowid")?;
+		ownership.co_owners = statement
+			.query_map([channel.get() as i64], |row| Ok(UserId::new(row.get::<_, i64>(0)? as u64)))?
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(Some(ownership))
+	}
+
+	/// Stores the ownership, unless the channel is already known.
+	pub fn insert(&self, db: &Database) -> Result<(), Error> {
+		db.lock().execute(
+			"INSERT OR IGNORE INTO channels (channel_id, owner_id, creator_id, created_at) VALUES (?1, ?2, ?3, ?4)",
+			(
+				self.channel.get() as i64,
+				self.owner.get() as i64,
+				self.creator.get() as i64,
+				self.created_at,
+			),
+		)?;
+		Ok(())
+	}
+
+	/// Changes the owner of the given channel, creating the record if the channel was not known yet. If the new owner was
+	/// a co-owner, they are no longer listed as such.
+	pub fn set_owner(db: &Database, channel: ChannelId, owner: UserId) -> Result<(), Error> {
+		ChannelOwnership::new(channel, owner).insert(db)?;
+		db.lock().execute(
+			"UPDATE channels SET owner_id = ?2 WHERE channel_id = ?1",
+			(channel.get() as i64, owner.get() as i64),
+		)?;
+		ChannelOwnership::remove_co_owner(db, channel, owner)?;
+		Ok(())
+	}
+
+	/// Adds a co-owner to a channel, which must already be known. Returns `false` if the user already was a co-owner.
+	pub fn add_co_owner(db: &Database, channel: ChannelId, user: UserId) -> Result<bool, Error> {
+		let inserted = db.lock().execute(
+			"INSERT OR IGNORE INTO channel_co_owners (channel_id, user_id) VALUES (?1, ?2)",
+			(channel.get() as i64, user.get() as i64),
+		)?;
+		Ok(inserted > 0)
+	}
+
+	/// Removes a co-owner from a channel. Returns `false` if the user was no co-owner.
+	pub fn remove_co_owner(db: &Database, channel: ChannelId, user: UserId) -> Result<bool, Error> {
+		let deleted = db.lock().execute(
+			"DELETE FROM channel_co_owners WHERE channel_id = ?1 AND user_id = ?2",
+			(channel.get() as i64, user.get() as i64),
+		)?;
+		Ok(deleted > 0)
+	}
+
+	pub fn delete(db: &Database, channel: ChannelId) -> Result<(), Error> {
+		db.lock()
+			.execute("DELETE FROM channels WHERE channel_id = ?1", [channel.get() as i64])?;
+		Ok(())
+	}
+
+	/// Returns all channels owned by the given user. Might contain channels which no longer exist.
+	pub fn owned_by(db: &Database, owner: UserId) -> Result<Vec<ChannelId>, Error> {
+		let db = db.lock();
+		let mut statement = db.prepare("SELECT channel_id FROM channels WHERE owner_id = ?1")?;
+		let channels = statement
+			.query_map([owner.get() as i64], |row| Ok(ChannelId::new(row.get::<_, i64>(0)? as u64)))?
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(channels)
+	}
+}
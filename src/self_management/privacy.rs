@@ -0,0 +1,105 @@
+use poise::serenity_prelude::{
+	CacheHttp,
+	GuildChannel,
+	PermissionOverwrite,
+	PermissionOverwriteType,
+	Permissions,
+	RoleId,
+	UserId,
+};
+
+use crate::{
+	config::Config,
+	Error,
+};
+
+/// Changes only the view permission of an overwrite, all other permissions configured for the channel are kept. The
+/// given channel is updated as well, so several changes can be made in a row.
+pub async fn set_view_permission(
+	http: impl CacheHttp,
+	channel: &mut GuildChannel,
+	kind: PermissionOverwriteType,
+	view: Option<bool>,
+) -> Result<(), Error> {
+	set_permissions(http, channel, kind, Permissions::VIEW_CHANNEL, view).await
+}
+
+/// Allows (`Some(true)`), denies (`Some(false)`) or resets (`None`) the given permissions of an overwrite, leaving all
+/// other permissions untouched.
+pub async fn set_permissions(
+	http: impl CacheHttp,
+	channel: &mut GuildChannel,
+	kind: PermissionOverwriteType,
+	permissions: Permissions,
+	value: Option<bool>,
+) -> Result<(), Error> {
+	let index = channel.permission_overwrites.iter().position(|o| o.kind == kind);
+	let mut overwrite = match index {
+		Some(index) => channel.permission_overwrites.remove(index),
+		None => PermissionOverwrite {
+			allow: Permissions::empty(),
+			deny: Permissions::empty(),
+			kind,
+		},
+	};
+
+	overwrite.allow.remove(permissions);
+	overwrite.deny.remove(permissions);
+	match value {
+		Some(true) => overwrite.allow.insert(permissions),
+		Some(false) => overwrite.deny.insert(permissions),
+		None => {},
+	}
+
+	// empty overwrites are removed altogether to keep the channel settings tidy
+	if overwrite.allow.is_empty() && overwrite.deny.is_empty() {
+		if index.is_some() {
+			channel.id.delete_permission(http.http(), kind).await?;
+		}
+	} else {
+		channel.id.create_permission(http.http(), overwrite.clone()).await?;
+		channel.permission_overwrites.push(overwrite);
+	}
+
+	Ok(())
+}
+
+/// Hides the channel from everyone except the given members.
+pub async fn make_private(http: impl CacheHttp, channel: &mut GuildChannel, members: Vec<UserId>) -> Result<(), Error> {
+	// members are allowed first, so nobody loses access in between
+	for member in members {
+		set_view_permission(&http, channel, PermissionOverwriteType::Member(member), Some(true)).await?;
+	}
+
+	let everyone = PermissionOverwriteType::Role(channel.guild_id.everyone_role());
+	set_view_permission(&http, channel, everyone, Some(false)).await
+}
+
+/// Channels are private if everyone is denied to see them.
+pub fn is_private(channel: &GuildChannel) -> bool {
+	let everyone = PermissionOverwriteType::Role(channel.guild_id.everyone_role());
+	channel
+		.permission_overwrites
+		.iter()
+		.any(|o| o.kind == everyone && o.deny.contains(Permissions::VIEW_CHANNEL))
+}
+
+/// Allows the user to see the channel if it is private. Used whenever someone becomes responsible for a channel.
+pub async fn grant_access(http: impl CacheHttp, channel: &GuildChannel, user: UserId) -> Result<(), Error> {
+	if !is_private(channel) {
+		return Ok(());
+	}
+
+	let mut channel = channel.clone();
+	set_view_permission(http, &mut channel, PermissionOverwriteType::Member(user), Some(true)).await
+}
+
+/// Roles private channels may be restricted to, which are the ones members can pick themselves.
+pub fn lockable_roles(config: &Config) -> Vec<RoleId> {
+	config
+		.assignments
+		.values()
+		.flat_map(|assignment| &assignment.roles)
+		.map(|role| RoleId::new(role.role))
+		.collect()
+}
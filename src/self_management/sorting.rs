@@ -0,0 +1,170 @@
+use std::{
+	cmp::Reverse,
+	collections::HashSet,
+	time::Duration,
+};
+
+#[allow(unused_imports)]
+use log::{
+	debug,
+	error,
+	info,
+	trace,
+	warn,
+};
+use poise::serenity_prelude::{
+	ChannelId,
+	Context,
+	GuildChannel,
+	UserId,
+};
+use serde_json::json;
+
+use super::{
+	self_managed_channels,
+	CATEGORY_CHANNEL_LIMIT,
+};
+use crate::{
+	config::{
+		SelfManagement,
+		SortStrategy,
+	},
+	database::Database,
+	AppState,
+	Error,
+};
+
+/// Time without further sort requests before channels are actually sorted, so a burst of changes causes a single
+/// reorder only.
+const SORT_DEBOUNCE: Duration = Duration::from_secs(30);
+
+/// Self-managed channel which moderators pinned to the top of the list.
+#[derive(Debug)]
+pub struct PinnedChannel {
+	pub channel: ChannelId,
+	pub pinned_by: UserId,
+	pub pinned_at: i64,
+}
+
+impl PinnedChannel {
+	/// Returns `false` if the channel was already pinned.
+	pub fn insert(&self, db: &Database) -> Result<bool, Error> {
+		let inserted = db.lock().execute(
+			"INSERT OR IGNORE INTO pinned_channels (channel_id, pinned_by, pinned_at) VALUES (?1, ?2, ?3)",
+			(self.channel.get() as i64, self.pinned_by.get() as i64, self.pinned_at),
+		)?;
+		Ok(inserted > 0)
+	}
+
+	/// Returns `false` if the channel was not pinned.
+	pub fn delete(db: &Database, channel: ChannelId) -> Result<bool, Error> {
+		let deleted = db
+			.lock()
+			.execute("DELETE FROM pinned_channels WHERE channel_id = ?1", [channel.get() as i64])?;
+		Ok(deleted > 0)
+	}
+
+	pub fn all(db: &Database) -> Result<HashSet<ChannelId>, Error> {
+		let db = db.lock();
+		let mut statement = db.prepare("SELECT channel_id FROM pinned_channels")?;
+		let channels = statement
+			.query_map([], |row| Ok(ChannelId::new(row.get::<_, i64>(0)? as u64)))?
+			.collect::<Result<HashSet<_>, _>>()?;
+		Ok(channels)
+	}
+}
+
+/// Schedules sorting of the self-managed channels. Requests are debounced, so this is cheap to call after every change.
+pub fn request_sort(app: &AppState) {
+	app.sort_requests.notify_one();
+}
+
+pub fn spawn_sort_task(ctx: &Context, app: &AppState) {
+	let ctx = ctx.clone();
+	let app = app.clone();
+	tokio::spawn(async move {
+		loop {
+			app.sort_requests.notified().await;
+			// wait until requests stop coming in
+			while tokio::time::timeout(SORT_DEBOUNCE, app.sort_requests.notified())
+				.await
+				.is_ok()
+			{}
+
+			if let Err(err) = sort_channels(&ctx, &app).await {
+				error!("Failed to sort self-managed channels: {}", err);
+			}
+		}
+	});
+}
+
+/// Orders the self-managed channels according to the configured strategy and spreads them over the categories.
+async fn sort_channels(ctx: &Context, app: &AppState) -> Result<(), Error> {
+	let config = app.config();
+	let sm = &config.self_managment;
+
+	let mut channels = self_managed_channels(ctx, sm).await?;
+	let Some(guild) = channels.first().map(|c| c.guild_id) else {
+		return Ok(());
+	};
+
+	let category_index = |c: &GuildChannel| c.parent_id.and_then(|id| sm.category.iter().position(|c| *c == id.get()));
+	channels.sort_by_key(|c| (category_index(c), c.position, c.id));
+	let current = channels.iter().map(|c| c.id).collect::<Vec<_>>();
+
+	match sm.sorting {
+		SortStrategy::Alphabetical => channels.sort_by(|x, y| x.name.cmp(&y.name)),
+		// snowflakes contain their creation time, so newer messages have larger ids
+		SortStrategy::Activity => channels.sort_by_key(|c| Reverse(c.last_message_id.map_or(c.id.get(), |id| id.get()))),
+		SortStrategy::Created => channels.sort_by_key(|c| c.id),
+		SortStrategy::PinnedFirst => {
+			let pinned = PinnedChannel::all(&app.db)?;
+			channels.sort_by(|x, y| {
+				pinned
+					.contains(&y.id)
+					.cmp(&pinned.contains(&x.id))
+					.then_with(|| x.name.cmp(&y.name))
+			});
+		},
+	}
+
+	let targets = channels
+		.iter()
+		.enumerate()
+		.map(|(idx, channel)| (channel, target_category(sm, idx)))
+		.collect::<Vec<_>>();
+
+	// avoid spending the rather strict rate limit on a no-op
+	let unchanged = targets.iter().all(|(channel, category)| channel.parent_id == Some(*category))
+		&& current.iter().eq(channels.iter().map(|c| &c.id));
+	if unchanged {
+		trace!("Self-managed channels are already sorted");
+		return Ok(());
+	}
+
+	debug!("Sorting {} self-managed channels by {:?}", channels.len(), sm.sorting);
+	let positions = targets
+		.into_iter()
+		.enumerate()
+		.map(|(idx, (channel, category))| {
+			let mut position = json!({
+				"id": channel.id,
+				"position": idx,
+			});
+			if channel.parent_id != Some(category) {
+				position["parent_id"] = json!(category.to_string());
+			}
+			position
+		})
+		.collect::<Vec<_>>();
+
+	// unlike `GuildId::reorder_channels`, this allows moving channels between categories in the same request
+	ctx.http.edit_guild_channel_positions(guild, &positions.into()).await?;
+
+	Ok(())
+}
+
+/// Fills up categories in order, which also rebalances channels if categories were added or removed.
+fn target_category(sm: &SelfManagement, idx: usize) -> ChannelId {
+	ChannelId::new(sm.category[(idx / CATEGORY_CHANNEL_LIMIT).min(sm.category.len() - 1)])
+}
@@ -0,0 +1,195 @@
+#[allow(unused_imports)]
+use log::{
+	debug,
+	error,
+	info,
+	trace,
+	warn,
+};
+use poise::serenity_prelude::{
+	ChannelId,
+	ChannelType,
+	Context,
+	CreateMessage,
+	GuildChannel,
+	Mentionable,
+	MessageId,
+	UserId,
+};
+use rusqlite::OptionalExtension;
+
+use super::{
+	archive_channel,
+	last_activity,
+	last_message_id,
+	log_both_as,
+	ownership::ChannelOwnership,
+	self_managed_channels,
+};
+use crate::{
+	config::Sweeper,
+	database::{
+		self,
+		Database,
+	},
+	AppState,
+	Error,
+};
+
+/// Warning that was posted into an inactive channel, announcing its removal.
+#[derive(Debug)]
+pub struct InactivityWarning {
+	pub channel: ChannelId,
+	pub message: MessageId,
+	/// Last message before the warning, unknown for warnings posted by older versions.
+	pub previous_message: Option<MessageId>,
+	pub warned_at: i64,
+}
+
+impl InactivityWarning {
+	pub fn load(db: &Database, channel: ChannelId) -> Result<Option<Self>, Error> {
+		let warning = db
+			.lock()
+			.query_row(
+				"SELECT message_id, previous_message_id, warned_at FROM inactivity_warnings WHERE channel_id = ?1",
+				[channel.get() as i64],
+				|row| {
+					Ok(InactivityWarning {
+						channel,
+						message: MessageId::new(row.get::<_, i64>(0)? as u64),
+						previous_message: row.get::<_, Option<i64>>(1)?.map(|id| MessageId::new(id as u64)),
+						warned_at: row.get(2)?,
+					})
+				},
+			)
+			.optional()?;
+		Ok(warning)
+	}
+
+	pub fn insert(&self, db: &Database) -> Result<(), Error> {
+		db.lock().execute(
+			"INSERT OR REPLACE INTO inactivity_warnings (channel_id, message_id, previous_message_id, warned_at) VALUES (?1, ?2, ?3, \
+			 ?4)",
+			(
+				self.channel.get() as i64,
+				self.message.get() as i64,
+				self.previous_message.map(|id| id.get() as i64),
+				self.warned_at,
+			),
+		)?;
+		Ok(())
+	}
+
+	pub fn delete(db: &Database, channel: ChannelId) -> Result<(), Error> {
+		db.lock().execute(
+			"DELETE FROM inactivity_warnings WHERE channel_id = ?1",
+			[channel.get() as i64],
+		)?;
+		Ok(())
+	}
+}
+
+/// Warns inactive channels in the self-managed category and removes them if they are still inactive after the grace
+/// period.
+pub async fn sweep_inactive_channels(ctx: &Context, app: &AppState) -> Result<(), Error> {
+	let config = app.config();
+	let Some(sweeper) = &config.self_managment.sweeper else {
+		return Ok(());
+	};
+
+	for channel in self_managed_channels(ctx, &config.self_managment).await? {
+		if channel.kind != ChannelType::Text {
+			continue;
+		}
+
+		// a single broken channel should not prevent the others from being processed
+		if let Err(err) = sweep_channel(ctx, app, &channel, sweeper).await {
+			error!("Failed to sweep channel {} ({}): {}", channel.name, channel.id, err);
+		}
+	}
+
+	Ok(())
+}
+
+async fn sweep_channel(ctx: &Context, app: &AppState, channel: &GuildChannel, sweeper: &Sweeper) -> Result<(), Error> {
+	let config = app.config();
+	let sm = &config.self_managment;
+	let now = database::now();
+	let last_message = last_message_id(&app.db, channel)?;
+
+	match InactivityWarning::load(&app.db, channel.id)? {
+		// someone wrote after our warning, so the channel is alive again
+		Some(warning) if last_message != warning.previous_message => {
+			debug!("Channel {} ({}) is active again", channel.name, channel.id);
+			InactivityWarning::delete(&app.db, channel.id)?;
+		},
+
+		// grace period is over
+		Some(warning) if now - warning.warned_at >= sweeper.grace_period as i64 => {
+			info!("Removing inactive channel {} ({})", channel.name, channel.id);
+
+			let summary = match &sm.archive {
+				Some(archive) => {
+					let bot = ctx.cache.current_user().id;
+					archive_channel(ctx, &app.db, channel, archive, bot).await?;
+					"Inaktiven Kanal archiviert"
+				},
+				None => {
+					channel.id.delete(ctx).await?;
+					ChannelOwnership::delete(&app.db, channel.id)?;
+					"Inaktiven Kanal gelöscht"
+				},
+			};
+			InactivityWarning::delete(&app.db, channel.id)?;
+
+			log_both_as(ctx, app, None, summary, Some(channel), None, &[]).await?;
+		},
+
+		// still waiting for grace period to pass
+		Some(_) => {},
+
+		None => {
+			let last_activity = last_activity(&app.db, channel)?;
+			if now - last_activity < sweeper.inactive_after as i64 {
+				return Ok(());
+			}
+
+			info!("Warning inactive channel {} ({})", channel.name, channel.id);
+
+			let owner = ChannelOwnership::load(&app.db, channel.id)?.map(|ownership| ownership.owner);
+			let message = channel
+				.send_message(
+					ctx,
+					CreateMessage::default().content(warning_text(owner, last_activity, now, sweeper)),
+				)
+				.await?;
+
+			InactivityWarning {
+				channel: channel.id,
+				message: message.id,
+				previous_message: last_message,
+				warned_at: now,
+			}
+			.insert(&app.db)?;
+
+			log_both_as(ctx, app, None, "Inaktiven Kanal verwarnt", None, Some(channel), &[]).await?;
+		},
+	}
+
+	Ok(())
+}
+
+fn warning_text(owner: Option<UserId>, last_activity: i64, now: i64, sweeper: &Sweeper) -> String {
+	let text = format!(
+		"Dieser Kanal ist seit <t:{}:R> inaktiv und wird <t:{}:R> entfernt. Schreibe einfach eine Nachricht, falls der Kanal \
+		 erhalten bleiben soll.",
+		last_activity,
+		now + sweeper.grace_period as i64
+	);
+
+	// ping owner, so they get a chance to react
+	match owner {
+		Some(owner) => format!("{} {}", owner.mention(), text),
+		None => text,
+	}
+}
@@ -0,0 +1,241 @@
+#[allow(unused_imports)]
+use log::{
+	debug,
+	error,
+	info,
+	trace,
+	warn,
+};
+use poise::serenity_prelude::{
+	ChannelId,
+	ChannelType,
+	Context,
+	CreateChannel,
+	GuildId,
+	Member,
+	VoiceState,
+};
+use rusqlite::OptionalExtension;
+
+use super::{
+	category_with_room,
+	check_channel_name_as,
+	is_not_found,
+	log_both_as,
+	log_detailed,
+	ownership::ChannelOwnership,
+	sorting,
+	MemberPolicy,
+};
+use crate::{
+	config::VoiceLobby,
+	database::{
+		self,
+		Database,
+	},
+	AppState,
+	Error,
+};
+
+/// Voice room created by joining the lobby, which only exists as long as someone is in it.
+#[derive(Debug)]
+pub struct TemporaryRoom {
+	pub channel: ChannelId,
+	pub created_at: i64,
+}
+
+impl TemporaryRoom {
+	pub fn load(db: &Database, channel: ChannelId) -> Result<Option<Self>, Error> {
+		let room = db
+			.lock()
+			.query_row(
+				"SELECT created_at FROM temporary_rooms WHERE channel_id = ?1",
+				[channel.get() as i64],
+				|row| {
+					Ok(TemporaryRoom {
+						channel,
+						created_at: row.get(0)?,
+					})
+				},
+			)
+			.optional()?;
+		Ok(room)
+	}
+
+	pub fn insert(&self, db: &Database) -> Result<(), Error> {
+		db.lock().execute(
+			"INSERT INTO temporary_rooms (channel_id, created_at) VALUES (?1, ?2)",
+			(self.channel.get() as i64, self.created_at),
+		)?;
+		Ok(())
+	}
+
+	pub fn delete(db: &Database, channel: ChannelId) -> Result<(), Error> {
+		db.lock()
+			.execute("DELETE FROM temporary_rooms WHERE channel_id = ?1", [channel.get() as i64])?;
+		Ok(())
+	}
+
+	pub fn all(db: &Database) -> Result<Vec<Self>, Error> {
+		let db = db.lock();
+		let mut statement = db.prepare("SELECT channel_id, created_at FROM temporary_rooms")?;
+		let rooms = statement
+			.query_map([], |row| {
+				Ok(TemporaryRoom {
+					channel: ChannelId::new(row.get::<_, i64>(0)? as u64),
+					created_at: row.get(1)?,
+				})
+			})?
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(rooms)
+	}
+}
+
+/// Spawns rooms for users joining the lobby and removes rooms which have been left empty.
+pub async fn handle_voice_state_update(
+	ctx: &Context,
+	app: &AppState,
+	old: Option<&VoiceState>,
+	new: &VoiceState,
+) -> Result<(), Error> {
+	let Some(guild) = new.guild_id else {
+		return Ok(());
+	};
+
+	// leaving a room might have left it empty
+	if let Some(left) = old.and_then(|old| old.channel_id) {
+		if new.channel_id != Some(left) && TemporaryRoom::load(&app.db, left)?.is_some() {
+			remove_if_empty(ctx, app, guild, left).await?;
+		}
+	}
+
+	let config = app.config();
+	let Some(lobby) = &config.self_managment.voice_lobby else {
+		return Ok(());
+	};
+	if new.channel_id != Some(ChannelId::new(lobby.channel)) {
+		return Ok(());
+	}
+	let Some(member) = &new.member else {
+		return Ok(());
+	};
+	if member.user.bot {
+		return Ok(());
+	}
+
+	create_room(ctx, app, guild, member, lobby).await
+}
+
+async fn create_room(ctx: &Context, app: &AppState, guild: GuildId, member: &Member, lobby: &VoiceLobby) -> Result<(), Error> {
+	let config = app.config();
+	let sm = &config.self_managment;
+
+	// rooms are owned like any other channel, so the same policies apply
+	if MemberPolicy::check(ctx, app, member).await?.is_some() {
+		debug!(
+			"Not creating voice room for {}, since their policy does not allow another channel",
+			member.user.name
+		);
+		guild.disconnect_member(ctx, member.user.id).await?;
+		return Ok(());
+	}
+
+	// names are validated like the ones users choose themselves, falling back to the unique username if the display
+	// name is taken or not allowed
+	let mut name = None;
+	for candidate in [member.display_name(), member.user.name.as_str()] {
+		let candidate = lobby.name.replace("{user}", candidate);
+		if check_channel_name_as(ctx, app, &member.user, guild, &candidate, None)
+			.await
+			.is_ok()
+		{
+			name = Some(candidate);
+			break;
+		}
+	}
+	let Some(name) = name else {
+		debug!(
+			"Not creating voice room for {}, since no valid name was found",
+			member.user.name
+		);
+		guild.disconnect_member(ctx, member.user.id).await?;
+		return Ok(());
+	};
+
+	let category = category_with_room(&guild.channels(ctx).await?, sm)?;
+	let room = guild
+		.create_channel(ctx, CreateChannel::new(name).kind(ChannelType::Voice).category(category))
+		.await?;
+	TemporaryRoom {
+		channel: room.id,
+		created_at: database::now(),
+	}
+	.insert(&app.db)?;
+	ChannelOwnership::new(room.id, member.user.id).insert(&app.db)?;
+	info!("Created voice room {} ({}) for {}", room.name, room.id, member.user.name);
+
+	// user might have left the lobby already, leaving the room empty right away
+	if let Err(err) = guild.move_member(ctx, member.user.id, room.id).await {
+		warn!("Failed to move {} into voice room {}: {}", member.user.name, room.id, err);
+		remove_if_empty(ctx, app, guild, room.id).await?;
+		return Ok(());
+	}
+
+	log_detailed(ctx, app, Some(&member.user), "Sprachraum erstellt", &room, &[]).await?;
+	sorting::request_sort(app);
+
+	Ok(())
+}
+
+/// Deletes the room unless someone is still connected. Without cached voice states, rooms are left alone.
+async fn remove_if_empty(ctx: &Context, app: &AppState, guild: GuildId, room: ChannelId) -> Result<(), Error> {
+	let is_empty = ctx
+		.cache
+		.guild(guild)
+		.is_some_and(|guild| !guild.voice_states.values().any(|state| state.channel_id == Some(room)));
+	if !is_empty {
+		return Ok(());
+	}
+
+	debug!("Removing empty voice room {}", room);
+	let channel = room.delete(ctx).await?;
+	TemporaryRoom::delete(&app.db, room)?;
+	ChannelOwnership::delete(&app.db, room)?;
+
+	if let Some(channel) = channel.guild() {
+		log_both_as(ctx, app, None, "Leeren Sprachraum gelöscht", Some(&channel), None, &[]).await?;
+	}
+
+	Ok(())
+}
+
+/// Removes rooms which have been left while the bot was offline or events got lost.
+pub async fn remove_empty_rooms(ctx: &Context, app: &AppState) -> Result<(), Error> {
+	for room in TemporaryRoom::all(&app.db)? {
+		let channel = match room.channel.to_channel(ctx).await {
+			Ok(channel) => channel.guild(),
+			// room has been deleted by someone else in the meantime
+			Err(err) if is_not_found(&err) => {
+				warn!("Forgetting voice room {}, since it no longer exists", room.channel);
+				TemporaryRoom::delete(&app.db, room.channel)?;
+				ChannelOwnership::delete(&app.db, room.channel)?;
+				continue;
+			},
+			// transient errors are retried next time
+			Err(err) => {
+				warn!("Failed to fetch voice room {}: {}", room.channel, err);
+				continue;
+			},
+		};
+		let Some(channel) = channel else {
+			continue;
+		};
+
+		if let Err(err) = remove_if_empty(ctx, app, channel.guild_id, channel.id).await {
+			warn!("Failed to remove empty voice room {}: {}", channel.id, err);
+			continue;
+		}
+	}
+
+	Ok(())
+}
@@ -1,4 +1,10 @@
-use std::collections::HashSet;
+use std::{
+	collections::{
+		HashMap,
+		HashSet,
+	},
+	sync::Mutex,
+};
 
 #[allow(unused_imports)]
 use log::{
@@ -11,22 +17,29 @@ use log::{
 use poise::{
 	serenity_prelude::{
 		ButtonStyle,
+		ChannelId,
 		ChannelType,
 		CreateActionRow,
 		CreateSelectMenu,
 		CreateSelectMenuOption,
 		GuildChannel,
 		Message,
+		MessageFlags,
+		MessageId,
 		RoleId,
 	},
 	Command,
 	CreateReply,
 };
+use rusqlite::OptionalExtension;
 use serenity::{
 	all::{
 		ComponentInteraction,
 		ComponentInteractionDataKind,
+		CreateAttachment,
 		CreateButton,
+		CreateEmbed,
+		CreateEmbedFooter,
 		CreateInteractionResponse,
 		CreateInteractionResponseMessage,
 		CreateMessage,
@@ -40,41 +53,339 @@ use serenity::{
 };
 
 use crate::{
+	config::{
+		AttachmentFile,
+		FileReference,
+		TableOfContentEntry,
+	},
+	database::{
+		self,
+		Database,
+	},
+	limits::{
+		ACTION_ROWS,
+		BUTTONS_PER_ROW,
+		EMBED_DESCRIPTION_LENGTH,
+		EMBED_LENGTH,
+		MESSAGE_LENGTH,
+		SELECT_MENU_OPTIONS,
+	},
 	AppState,
 	Context,
 	Error,
 };
 
+/// Separates the filenames of nested entries in custom ids, like `toc:studies.md>exams.md`.
+const PATH_SEPARATOR: char = '>';
+/// Separates the page of a toc entry which is too long for a single message, like `toc:studies.md@2`.
+const PAGE_SEPARATOR: char = '@';
+
+/// Part of a file, small enough to be sent as a single message.
+pub enum Part {
+	Text(String),
+	Embed(Box<CreateEmbed>),
+}
+
+impl Part {
+	fn message(&self) -> CreateMessage {
+		match self {
+			Part::Text(content) => CreateMessage::default().content(content),
+			Part::Embed(embed) => CreateMessage::default().embed(*embed.clone()),
+		}
+	}
+
+	/// Replaces the previous content of a message, which might have been of the other kind.
+	fn edit(&self) -> EditMessage {
+		match self {
+			Part::Text(content) => EditMessage::default().content(content).embeds(vec![]).suppress_embeds(true),
+			Part::Embed(embed) => EditMessage::default()
+				.content("")
+				.embeds(vec![*embed.clone()])
+				.suppress_embeds(false),
+		}
+	}
+
+	fn response(&self) -> CreateInteractionResponseMessage {
+		match self {
+			Part::Text(content) => CreateInteractionResponseMessage::new().content(content).embeds(vec![]),
+			Part::Embed(embed) => CreateInteractionResponseMessage::new()
+				.content("")
+				.embeds(vec![*embed.clone()]),
+		}
+	}
+}
+
+/// Splits a file into parts, which are rendered as embeds if the file has a front-matter. Title, color and thumbnail
+/// are repeated on every part, while fields and footer are only added to the last one.
+pub fn split_file(file: &FileReference) -> Vec<Part> {
+	let Some(front_matter) = &file.front_matter else {
+		return split_content(&file.content, MESSAGE_LENGTH)
+			.into_iter()
+			.map(Part::Text)
+			.collect();
+	};
+
+	let limit = EMBED_DESCRIPTION_LENGTH.min(EMBED_LENGTH.saturating_sub(front_matter.length()).max(1));
+	let descriptions = split_content(&file.content, limit);
+	let count = descriptions.len();
+	descriptions
+		.into_iter()
+		.enumerate()
+		.map(|(idx, description)| {
+			let mut embed = CreateEmbed::new().description(description);
+			if let Some(title) = &front_matter.title {
+				embed = embed.title(title);
+			}
+			if let Some(color) = front_matter.color {
+				embed = embed.color(color);
+			}
+			if let Some(thumbnail) = &front_matter.thumbnail {
+				embed = embed.thumbnail(thumbnail);
+			}
+			if idx + 1 == count {
+				embed = embed.fields(
+					front_matter
+						.fields
+						.iter()
+						.map(|field| (&field.name, &field.value, field.inline)),
+				);
+				if let Some(footer) = &front_matter.footer {
+					embed = embed.footer(CreateEmbedFooter::new(footer));
+				}
+			}
+			Part::Embed(Box::new(embed))
+		})
+		.collect()
+}
+
+/// Splits content into messages within the given length limit. Splits happen at headings if possible, otherwise at
+/// paragraphs, lines or, as last resort, anywhere.
+fn split_content(content: &str, limit: usize) -> Vec<String> {
+	let mut messages = Vec::new();
+	split_into(content, limit, 0, &mut messages);
+
+	let messages = messages
+		.into_iter()
+		.map(|message| message.trim().to_string())
+		.filter(|message| !message.is_empty())
+		.collect::<Vec<_>>();
+	match messages.is_empty() {
+		true => vec![String::new()],
+		false => messages,
+	}
+}
+
+fn split_into(content: &str, limit: usize, level: usize, messages: &mut Vec<String>) {
+	let pieces = match level {
+		0 => split_at_headings(content),
+		1 => content.split_inclusive("\n\n").map(String::from).collect(),
+		2 => content.split_inclusive('\n').map(String::from).collect(),
+		_ => {
+			let chars = content.chars().collect::<Vec<_>>();
+			chars.chunks(limit).map(|chunk| chunk.iter().collect()).collect()
+		},
+	};
+
+	// greedily fill messages, pieces which are too long on their own are split further
+	let mut current = String::new();
+	for piece in pieces {
+		if current.chars().count() + piece.chars().count() <= limit {
+			current.push_str(&piece);
+			continue;
+		}
+
+		if !current.is_empty() {
+			messages.push(std::mem::take(&mut current));
+		}
+		match piece.chars().count() <= limit {
+			true => current = piece,
+			false => split_into(&piece, limit, level + 1, messages),
+		}
+	}
+	if !current.is_empty() {
+		messages.push(current);
+	}
+}
+
+/// Splits markdown into sections, each starting with a heading.
+fn split_at_headings(content: &str) -> Vec<String> {
+	let mut sections = Vec::new();
+	let mut current = String::new();
+	for line in content.split_inclusive('\n') {
+		if line.starts_with('#') && !current.is_empty() {
+			sections.push(std::mem::take(&mut current));
+		}
+		current.push_str(line);
+	}
+	sections.push(current);
+	sections
+}
+
+/// Messages making up a welcome message, in order. Groups are identified by their first message.
+fn load_welcome_group(db: &Database, message: MessageId) -> Result<Option<(MessageId, Vec<MessageId>)>, Error> {
+	let db = db.lock();
+	let group = db
+		.query_row(
+			"SELECT group_id FROM welcome_messages WHERE message_id = ?1",
+			[message.get() as i64],
+			|row| row.get::<_, i64>(0),
+		)
+		.optional()?;
+	let Some(group) = group else {
+		return Ok(None);
+	};
+
+	let mut statement = db.prepare("SELECT message_id FROM welcome_messages WHERE group_id = ?1 ORDER BY part")?;
+	let messages = statement
+		.query_map([group], |row| Ok(MessageId::new(row.get::<_, i64>(0)? as u64)))?
+		.collect::<Result<Vec<_>, _>>()?;
+	Ok(Some((MessageId::new(group as u64), messages)))
+}
+
+fn save_welcome_group(db: &Database, group: MessageId, channel: ChannelId, messages: &[MessageId]) -> Result<(), Error> {
+	let mut db = db.lock();
+	let tx = db.transaction()?;
+	tx.execute("DELETE FROM welcome_messages WHERE group_id = ?1", [group.get() as i64])?;
+	for (part, message) in messages.iter().enumerate() {
+		tx.execute(
+			"INSERT OR REPLACE INTO welcome_messages (message_id, group_id, part, channel_id) VALUES (?1, ?2, ?3, ?4)",
+			(message.get() as i64, group.get() as i64, part as i64, channel.get() as i64),
+		)?;
+	}
+	tx.commit()?;
+	Ok(())
+}
+
+/// How long uploaded attachments are reused, if their url does not tell when it expires.
+const ATTACHMENT_CACHE_DURATION: i64 = 60 * 60;
+/// Cached urls are dropped this long before they expire, so they are still valid when the user opens them.
+const ATTACHMENT_EXPIRY_MARGIN: i64 = 60 * 60;
+
+/// Urls of already uploaded toc attachments by their path, so repeated clicks don't upload the files again.
+#[derive(Default)]
+pub struct AttachmentCache {
+	urls: Mutex<HashMap<String, (String, i64)>>,
+}
+
+impl AttachmentCache {
+	fn get(&self, path: &str) -> Option<String> {
+		let mut urls = self.urls.lock().expect("attachment cache lock poisoned");
+		match urls.get(path) {
+			Some((url, expires_at)) if *expires_at > database::now() => Some(url.clone()),
+			Some(_) => {
+				urls.remove(path);
+				None
+			},
+			None => None,
+		}
+	}
+
+	fn insert(&self, path: &str, url: &str) {
+		// cdn urls are signed and carry their expiry as hex timestamp, like `?ex=6720f3c1&...`
+		let expires_at = url
+			.split(['?', '&'])
+			.find_map(|param| param.strip_prefix("ex="))
+			.and_then(|ex| i64::from_str_radix(ex, 16).ok())
+			.map(|ex| ex - ATTACHMENT_EXPIRY_MARGIN)
+			.unwrap_or_else(|| database::now() + ATTACHMENT_CACHE_DURATION);
+		self
+			.urls
+			.lock()
+			.expect("attachment cache lock poisoned")
+			.insert(path.to_string(), (url.to_string(), expires_at));
+	}
+
+	/// Forgets all uploads, since the files might have changed.
+	pub fn clear(&self) {
+		self.urls.lock().expect("attachment cache lock poisoned").clear();
+	}
+}
+
+/// Links an already uploaded attachment, images are shown right away.
+fn attachment_embed(attachment: &AttachmentFile, url: &str) -> CreateEmbed {
+	let extension = attachment
+		.filename
+		.rsplit_once('.')
+		.map(|(_, extension)| extension.to_lowercase());
+	match extension.as_deref() {
+		Some("png" | "jpg" | "jpeg" | "gif" | "webp") => CreateEmbed::new().image(url),
+		_ => CreateEmbed::new().title(&attachment.filename).url(url),
+	}
+}
+
 pub fn register_commands(commands: &mut Vec<Command<AppState, Error>>) {
 	commands.push(post_welcome_message());
 	commands.push(update_welcome_message());
 }
 
+/// Assigns each top level entry to a row of the welcome message, the first row starts with the assignments button.
+/// Entries with an explicit row are placed first and move on to the next row if theirs is full, the others fill the
+/// remaining gaps in order. Returns `None` if there are more entries than buttons fit.
+pub fn assign_rows(entries: &[TableOfContentEntry]) -> Option<Vec<usize>> {
+	let mut counts = [0; ACTION_ROWS];
+	counts[0] = 1;
+	let mut rows = vec![None; entries.len()];
+
+	let explicit = entries.iter().enumerate().filter(|(_, entry)| entry.row.is_some());
+	let automatic = entries.iter().enumerate().filter(|(_, entry)| entry.row.is_none());
+	for (idx, entry) in explicit.chain(automatic) {
+		let first = entry.row.map_or(0, |row| row.clamp(1, ACTION_ROWS) - 1);
+		let row = (first..ACTION_ROWS).find(|&row| counts[row] < BUTTONS_PER_ROW)?;
+		counts[row] += 1;
+		rows[idx] = Some(row);
+	}
+
+	rows.into_iter().collect()
+}
+
 fn get_toc_buttons(app: &AppState) -> Vec<CreateActionRow> {
+	let config = app.config();
+
+	let assignments = CreateButton::new("assignments")
+		.label(&config.self_assignments.label)
+		.emoji(config.self_assignments.icon.clone())
+		.style(ButtonStyle::Success);
+
+	// too many entries for buttons are offered in a select menu instead
+	let Some(assigned) = assign_rows(&config.toc) else {
+		let options = config
+			.toc
+			.iter()
+			.take(SELECT_MENU_OPTIONS)
+			.map(|entry| CreateSelectMenuOption::new(entry.label.clone(), entry.file.filename.clone()).emoji(entry.icon.to_owned()))
+			.collect();
+		let menu = CreateSelectMenu::new("toc_menu", CreateSelectMenuKind::String {
+			options,
+		})
+		.placeholder("Thema auswählen");
+		return vec![CreateActionRow::Buttons(vec![assignments]), CreateActionRow::SelectMenu(menu)];
+	};
+
 	// adds buttons for toc records
-	let mut buttons = Vec::new();
-	buttons.push(
-		CreateButton::new("assignments")
-			.label(&app.config.self_assignments.label)
-			.emoji(app.config.self_assignments.icon.clone())
-			.style(ButtonStyle::Success),
-	);
-	for entry in &app.config.toc {
-		buttons.push(
+	let mut rows = vec![Vec::new(); ACTION_ROWS];
+	rows[0].push(assignments);
+	for (entry, row) in config.toc.iter().zip(assigned) {
+		rows[row].push(
 			CreateButton::new(format!("toc:{}", entry.file.filename))
 				.label(&entry.label)
 				.emoji(entry.icon.to_owned())
 				.style(ButtonStyle::Primary),
 		);
 	}
-	vec![CreateActionRow::Buttons(buttons)]
+	rows
+		.into_iter()
+		.filter(|buttons| !buttons.is_empty())
+		.map(CreateActionRow::Buttons)
+		.collect()
 }
 
 /// Aktualisiert die verlinkte Nachricht auf die aktuelle Begrüßung.
+///
+/// Ist die Begrüßung auf mehrere Nachrichten aufgeteilt, kann eine beliebige davon angegeben werden.
 #[poise::command(prefix_command, rename = "rewelcome", required_permissions = "MANAGE_GUILD")]
 async fn update_welcome_message(
 	ctx: Context<'_>,
-	#[description = "Die Nachricht, welche aktualisiert werden soll."] mut message: Message,
+	#[description = "Die Nachricht, welche aktualisiert werden soll."] message: Message,
 ) -> Result<(), Error> {
 	let app = ctx.data();
 
@@ -85,15 +396,37 @@ async fn update_welcome_message(
 		return Err(Error::from("target message was not posted in this guild"));
 	}
 
-	message
-		.edit(
-			&ctx,
-			EditMessage::default()
-				.content(app.config.welcome.to_string())
-				.suppress_embeds(true)
-				.components(get_toc_buttons(app)),
-		)
-		.await?;
+	// messages posted before welcome messages were split are not known yet and form a group on their own
+	let channel = message.channel_id;
+	let (group, existing) = load_welcome_group(&app.db, message.id)?.unwrap_or((message.id, vec![message.id]));
+
+	let parts = split_file(&app.config().welcome);
+	let mut messages = Vec::new();
+	for (idx, part) in parts.iter().enumerate() {
+		// only the last message carries the buttons
+		let components = match idx + 1 == parts.len() {
+			true => get_toc_buttons(app),
+			false => vec![],
+		};
+
+		match existing.get(idx) {
+			Some(&id) => {
+				channel.edit_message(&ctx, id, part.edit().components(components)).await?;
+				messages.push(id);
+			},
+			None => {
+				let sent = channel.send_message(&ctx, part.message().components(components)).await?;
+				messages.push(sent.id);
+			},
+		}
+	}
+
+	// the welcome message got shorter, so surplus messages are removed
+	for &id in existing.iter().skip(parts.len()) {
+		channel.delete_message(&ctx, id).await?;
+	}
+
+	save_welcome_group(&app.db, group, channel, &messages)?;
 
 	ctx
 		.send(
@@ -123,14 +456,20 @@ async fn post_welcome_message(
 		return Err(Error::from("not a text channel"));
 	}
 
-	channel
-		.send_message(
-			&ctx,
-			CreateMessage::default()
-				.content(app.config.welcome.to_string())
-				.components(get_toc_buttons(app)),
-		)
-		.await?;
+	let parts = split_file(&app.config().welcome);
+	let mut messages = Vec::new();
+	for (idx, part) in parts.iter().enumerate() {
+		// only the last message carries the buttons
+		let components = match idx + 1 == parts.len() {
+			true => get_toc_buttons(app),
+			false => vec![],
+		};
+		let sent = channel.send_message(&ctx, part.message().components(components)).await?;
+		messages.push(sent.id);
+	}
+
+	// all parts are remembered, so `rewelcome` is able to update them together
+	save_welcome_group(&app.db, messages[0], channel.id, &messages)?;
 
 	ctx
 		.send(
@@ -163,7 +502,8 @@ pub async fn handle_assign_click<'a>(
 		.last()
 		.ok_or(format!("Unknown format in toc custom_id: {}", custom_id))?;
 
-	let assignment = app.config.assignments.get(id).ok_or(format!("Unknown assignment: {}", id))?;
+	let config = app.config();
+	let assignment = config.assignments.get(id).ok_or(format!("Unknown assignment: {}", id))?;
 	let member = interaction
 		.member
 		.as_ref()
@@ -251,6 +591,68 @@ Entfernte Rollen: {}
 	Ok(())
 }
 
+/// Looks up an entry by the filenames of itself and all its parents.
+fn find_entry<'a>(entries: &'a [TableOfContentEntry], path: &str) -> Option<&'a TableOfContentEntry> {
+	let (file, rest) = match path.split_once(PATH_SEPARATOR) {
+		Some((file, rest)) => (file, Some(rest)),
+		None => (path, None),
+	};
+
+	let entry = entries.iter().find(|e| e.file.filename == file)?;
+	match rest {
+		Some(rest) => find_entry(&entry.entries, rest),
+		None => Some(entry),
+	}
+}
+
+/// Buttons of a toc page, leading to the sub-entries, the other parts of a long page and back to the parent page, if
+/// any.
+fn get_page_buttons(path: &str, entry: &TableOfContentEntry, page: usize, pages: usize) -> Vec<CreateActionRow> {
+	let buttons = entry
+		.entries
+		.iter()
+		.map(|child| {
+			CreateButton::new(format!("toc:{}{}{}", path, PATH_SEPARATOR, child.file.filename))
+				.label(&child.label)
+				.emoji(child.icon.to_owned())
+				.style(ButtonStyle::Primary)
+		})
+		.collect::<Vec<_>>();
+	let mut rows = buttons
+		.chunks(BUTTONS_PER_ROW)
+		.map(|row| CreateActionRow::Buttons(row.to_vec()))
+		.collect::<Vec<_>>();
+
+	// top level pages are opened from the welcome message, so there is nothing to go back to
+	let mut navigation = Vec::new();
+	if let Some((parent, _)) = path.rsplit_once(PATH_SEPARATOR) {
+		navigation.push(
+			CreateButton::new(format!("toc:{}", parent))
+				.label("Zurück")
+				.style(ButtonStyle::Secondary),
+		);
+	}
+	if pages > 1 {
+		navigation.push(
+			CreateButton::new(format!("toc:{}{}{}", path, PAGE_SEPARATOR, page.saturating_sub(1)))
+				.label("◀")
+				.style(ButtonStyle::Secondary)
+				.disabled(page == 0),
+		);
+		navigation.push(
+			CreateButton::new(format!("toc:{}{}{}", path, PAGE_SEPARATOR, page + 1))
+				.label(format!("{}/{} ▶", page + 1, pages))
+				.style(ButtonStyle::Secondary)
+				.disabled(page + 1 >= pages),
+		);
+	}
+	if !navigation.is_empty() {
+		rows.push(CreateActionRow::Buttons(navigation));
+	}
+
+	rows
+}
+
 pub async fn handle_toc_click<'a>(
 	ctx: &'a poise::serenity_prelude::Context,
 	app: &'a AppState,
@@ -258,30 +660,72 @@ pub async fn handle_toc_click<'a>(
 ) -> Result<(), Error> {
 	let data = &interaction.data;
 
-	// assign buttons are identified as `assign:$file`
+	// toc buttons are identified as `toc:$file`, nested entries contain the files of their parents as well and pages
+	// of long entries are appended as `@$page`, while the select menu for many entries has the file as value
 	let custom_id = data.custom_id.as_str();
-	let file = custom_id
-		.splitn(2, ":")
-		.last()
-		.ok_or(format!("Unknown format in assign custom_id: {}", custom_id))?;
+	let path = match &data.kind {
+		ComponentInteractionDataKind::StringSelect {
+			values,
+		} => values.first().map(String::as_str),
+		_ => custom_id.split_once(':').map(|(_, path)| path),
+	}
+	.ok_or(format!("Unknown format in toc custom_id: {}", custom_id))?;
+	let (path, page) = match path.rsplit_once(PAGE_SEPARATOR) {
+		Some((path, page)) => (path, page.parse::<usize>()?),
+		None => (path, 0),
+	};
 
-	let entry = app
-		.config
-		.toc
-		.iter()
-		.find(|f| f.file.filename == file)
-		.ok_or(format!("Unknown toc file: {}", file))?;
+	let config = app.config();
+	let entry = find_entry(&config.toc, path).ok_or(format!("Unknown toc entry: {}", path))?;
+
+	let parts = split_file(&entry.file);
+	let page = page.min(parts.len() - 1);
+	let mut message = parts[page]
+		.response()
+		.components(get_page_buttons(path, entry, page, parts.len()));
+
+	// attachments belong to the last page, files are only uploaded if there is no recent upload to link to
+	let mut uploads = Vec::new();
+	if page + 1 == parts.len() {
+		for attachment in &entry.attachments {
+			match app.attachment_cache.get(&attachment.path) {
+				Some(url) => message = message.add_embed(attachment_embed(attachment, &url)),
+				None => {
+					message = message.add_file(CreateAttachment::bytes(attachment.data.clone(), &attachment.filename));
+					uploads.push(attachment);
+				},
+			}
+		}
+	}
 
-	interaction
-		.create_response(
-			ctx,
-			CreateInteractionResponse::Message(
-				CreateInteractionResponseMessage::new()
-					.content(entry.file.content.to_string())
-					.flags(poise::serenity_prelude::InteractionResponseFlags::EPHEMERAL),
+	// navigating within ephemeral pages replaces the page, instead of piling up messages
+	let is_page = interaction
+		.message
+		.flags
+		.is_some_and(|flags| flags.contains(MessageFlags::EPHEMERAL));
+	let response = match is_page {
+		true => CreateInteractionResponse::UpdateMessage(message),
+		false => CreateInteractionResponse::Message(message.flags(poise::serenity_prelude::InteractionResponseFlags::EPHEMERAL)),
+	};
+	interaction.create_response(ctx, response).await?;
+
+	if !uploads.is_empty() {
+		// the page is already shown, so failing to remember the uploads only costs another upload next time
+		match interaction.get_response(ctx).await {
+			Ok(message) if message.attachments.len() == uploads.len() => {
+				for (attachment, uploaded) in uploads.iter().zip(&message.attachments) {
+					app.attachment_cache.insert(&attachment.path, &uploaded.url);
+				}
+			},
+			Ok(message) => warn!(
+				"Expected {} attachments in toc page {}, but found {}",
+				uploads.len(),
+				path,
+				message.attachments.len()
 			),
-		)
-		.await?;
+			Err(err) => warn!("Failed to fetch toc page {} for caching its attachments: {}", path, err),
+		}
+	}
 
 	Ok(())
 }
@@ -291,10 +735,11 @@ pub async fn print_assignments<'a>(
 	app: &'a AppState,
 	interaction: &'a ComponentInteraction,
 ) -> Result<(), Error> {
+	let config = app.config();
 	let mut rows = Vec::new();
 
 	// add one row for each role assignment
-	for (id, assignment) in &app.config.assignments {
+	for (id, assignment) in &config.assignments {
 		let mut options = Vec::new();
 		for role in &assignment.roles {
 			let mut option = CreateSelectMenuOption::new(role.label.clone(), role.role.to_string()).emoji(role.icon.clone());
@@ -325,7 +770,7 @@ pub async fn print_assignments<'a>(
 			ctx,
 			CreateInteractionResponse::Message(
 				CreateInteractionResponseMessage::new()
-					.content(app.config.self_assignments.prolog.to_string())
+					.content(config.self_assignments.prolog.to_string())
 					.flags(poise::serenity_prelude::InteractionResponseFlags::EPHEMERAL)
 					.components(rows),
 			),